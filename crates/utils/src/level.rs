@@ -0,0 +1,175 @@
+use bevy::{asset::AssetPath, prelude::*, reflect::TypeUuid};
+use serde::{Deserialize, Serialize};
+
+use crate::{pickup::PickupKind, ron_asset::RonAsset};
+
+/// Level definition authored by designers in `assets/levels/<id>.level.ron`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, TypeUuid)]
+#[uuid = "5a7b1c52-2d8e-4d3b-9f0e-8e3a51f0c6a1"]
+pub struct LevelAsset {
+  pub name: String,
+  #[serde(default)]
+  pub skybox: Option<String>,
+  pub bounds: LevelBounds,
+  #[serde(default)]
+  pub spawn_points: Vec<SpawnPointDef>,
+  #[serde(default)]
+  pub props: Vec<PropDef>,
+  #[serde(default)]
+  pub enemies: Vec<EnemyDef>,
+  #[serde(default)]
+  pub hazards: Vec<HazardDef>,
+  #[serde(default)]
+  pub pickups: Vec<PickupDef>,
+  #[serde(default)]
+  pub objectives: Vec<ObjectiveDef>,
+}
+
+impl LevelAsset {
+  /// All external assets referenced by the level
+  pub fn asset_paths(&self) -> impl Iterator<Item = &str> {
+    self
+      .skybox
+      .iter()
+      .map(String::as_str)
+      .chain(self.props.iter().map(|p| p.model.as_str()))
+      .chain(self.enemies.iter().map(|e| e.model.as_str()))
+  }
+}
+
+/// Playable area on the XZ plane
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct LevelBounds {
+  pub min: Vec2,
+  pub max: Vec2,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SpawnPointDef {
+  pub position: Vec3,
+  /// rotation around Y in radians
+  #[serde(default)]
+  pub heading: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PropDef {
+  pub model: String,
+  pub position: Vec3,
+  #[serde(default)]
+  pub heading: f32,
+  #[serde(default = "default_scale")]
+  pub scale: f32,
+  /// props without a collider are purely decorative, the radius is scaled with the prop
+  #[serde(default)]
+  pub collider_radius: Option<f32>,
+  /// tagged props can be referenced by objectives
+  #[serde(default)]
+  pub tag: Option<String>,
+  /// tagged props with health can be destroyed, untagged props are always indestructible
+  #[serde(default)]
+  pub health: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct EnemyDef {
+  pub kind: String,
+  pub model: String,
+  pub position: Vec3,
+  #[serde(default)]
+  pub heading: f32,
+  #[serde(default)]
+  pub tag: Option<String>,
+  #[serde(default = "default_enemy_health")]
+  pub health: f32,
+  #[serde(default = "default_enemy_radius")]
+  pub collider_radius: f32,
+  /// pickups that may be left behind when the enemy is destroyed
+  #[serde(default)]
+  pub drops: Vec<DropDef>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct DropDef {
+  pub pickup: PickupKind,
+  /// between 0 and 1
+  #[serde(default = "default_drop_chance")]
+  pub chance: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PickupDef {
+  pub pickup: PickupKind,
+  pub position: Vec3,
+}
+
+/// Area that affects ships inside it
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct HazardDef {
+  pub kind: String,
+  pub position: Vec3,
+  pub radius: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum ObjectiveDef {
+  /// destroy `count` enemies, optionally only of the given kind
+  DestroyTargets {
+    count: u32,
+    #[serde(default)]
+    kind: Option<String>,
+  },
+  ReachWaypoint {
+    position: Vec3,
+    radius: f32,
+  },
+  Survive {
+    seconds: f32,
+  },
+  /// the level is lost if any entity with this tag is destroyed
+  Protect {
+    tag: String,
+  },
+}
+
+fn default_scale() -> f32 {
+  1.0
+}
+
+fn default_enemy_health() -> f32 {
+  30.0
+}
+
+fn default_enemy_radius() -> f32 {
+  5.0
+}
+
+fn default_drop_chance() -> f32 {
+  1.0
+}
+
+/// Picked in the menu, decides where a new game starts
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GameMode {
+  /// the level files in order
+  #[default]
+  Campaign,
+  /// an endless run of generated sectors
+  Sector,
+}
+
+pub fn level_path(level_id: u64) -> String {
+  format!("levels/{}.level.ron", level_id)
+}
+
+impl RonAsset for LevelAsset {
+  const EXTENSIONS: &'static [&'static str] = &["level.ron"];
+
+  /// start loading models and skybox together with the level
+  fn dependencies(&self) -> Vec<AssetPath<'static>> {
+    self
+      .asset_paths()
+      .map(|p| AssetPath::from(p).to_owned())
+      .collect()
+  }
+}
@@ -2,6 +2,10 @@ use bevy::prelude::*;
 
 pub mod vfx;
 pub mod game_time;
+pub mod level;
+pub mod loadout;
+pub mod pickup;
+pub mod ron_asset;
 pub mod ship;
 // pub mod grid;
 
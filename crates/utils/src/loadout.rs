@@ -0,0 +1,125 @@
+use bevy::{asset::AssetPath, prelude::*, reflect::TypeUuid};
+use serde::{Deserialize, Serialize};
+
+use crate::{ron_asset::RonAsset, ship::Spaceship};
+
+/// Ships the player can pick in the hangar, authored in `assets/ships/hangar.ships.ron`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, TypeUuid)]
+#[uuid = "e4f1a8d6-3c2b-4e7a-9d15-6b0f2c8a7e93"]
+pub struct ShipLibrary {
+  pub ships: Vec<ShipLoadout>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ShipLoadout {
+  pub name: String,
+  /// hull model
+  pub model: String,
+  #[serde(default = "default_health")]
+  pub health: f32,
+  #[serde(default)]
+  pub engine: Spaceship,
+  /// weapon names from the weapon library, cycled in this order
+  #[serde(default)]
+  pub weapons: Vec<String>,
+  #[serde(default)]
+  pub shield: ShieldDef,
+  #[serde(default)]
+  pub boost: BoostDef,
+  #[serde(default)]
+  pub turret: TurretDef,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ShieldDef {
+  pub max_energy: f32,
+  /// energy spent per second while the shield is up
+  pub drain: f32,
+  /// energy regained per second while the shield is down
+  pub recharge_rate: f32,
+  /// seconds after the shield went down before it starts to recharge
+  pub recharge_delay: f32,
+}
+
+impl Default for ShieldDef {
+  fn default() -> Self {
+    Self {
+      max_energy: 100.0,
+      drain: 25.0,
+      recharge_rate: 20.0,
+      recharge_delay: 2.0,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BoostDef {
+  pub max_energy: f32,
+  /// energy spent per boost
+  pub cost: f32,
+  /// energy regained per second while not boosting
+  pub recharge_rate: f32,
+  /// extra force along the heading while boosting
+  pub force: f32,
+  /// seconds a boost lasts
+  pub duration: f32,
+  /// seconds after a boost before the next one can start
+  pub cooldown: f32,
+}
+
+impl Default for BoostDef {
+  fn default() -> Self {
+    Self {
+      max_energy: 100.0,
+      cost: 35.0,
+      recharge_rate: 15.0,
+      force: 240000.0,
+      duration: 0.6,
+      cooldown: 1.5,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TurretDef {
+  /// radians per second
+  pub traverse_speed: f32,
+  /// how far the turret can turn away from the hull heading to either side, in radians, PI or
+  /// more turns all the way around
+  pub arc: f32,
+}
+
+impl Default for TurretDef {
+  fn default() -> Self {
+    Self {
+      traverse_speed: 6.0,
+      arc: std::f32::consts::PI,
+    }
+  }
+}
+
+fn default_health() -> f32 {
+  100.0
+}
+
+/// Loadout picked in the menu, the game falls back to its own defaults while this is `None`
+#[derive(Resource, Debug, Clone, Default)]
+pub struct SelectedLoadout(pub Option<ShipLoadout>);
+
+pub const SHIP_LIBRARY_PATH: &str = "ships/hangar.ships.ron";
+
+impl RonAsset for ShipLibrary {
+  const EXTENSIONS: &'static [&'static str] = &["ships.ron"];
+
+  /// hull models are shown in the hangar right away
+  fn dependencies(&self) -> Vec<AssetPath<'static>> {
+    self
+      .ships
+      .iter()
+      .map(|s| AssetPath::from(s.model.as_str()).to_owned())
+      .collect()
+  }
+}
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+/// What a pickup grants to the ship that flies through it
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum PickupKind {
+  /// restores hull, never above the maximum
+  Health(f32),
+  /// restores shield energy, never above the maximum
+  Shield(f32),
+  /// gives back this many shots to every weapon with limited ammo
+  Ammo(u32),
+  Modifier(ModifierDef),
+}
+
+/// Temporary change to a ship stat
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct ModifierDef {
+  pub stat: Stat,
+  /// the stat is multiplied by this while the modifier lasts
+  pub factor: f32,
+  pub seconds: f32,
+  #[serde(default)]
+  pub stacking: Stacking,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Stat {
+  FireRate,
+  /// engine thrust
+  Speed,
+}
+
+/// What happens when a ship picks up a modifier for a stat that is already modified
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum Stacking {
+  /// the new modifier replaces the old one
+  #[default]
+  Refresh,
+  /// the old modifier keeps its factor and lasts longer by the new duration
+  Extend,
+  /// applies on top with its own timer, up to this many at once, past that the modifier closest
+  /// to running out is refreshed
+  Stack(u32),
+}
@@ -0,0 +1,60 @@
+use std::marker::PhantomData;
+
+use bevy::{
+  asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset},
+  prelude::*,
+  reflect::TypeUuid,
+  utils::BoxedFuture,
+};
+use serde::de::DeserializeOwned;
+
+/// Asset read from a RON file by [`RonAssetLoader`]
+pub trait RonAsset: DeserializeOwned + TypeUuid + Send + Sync + 'static {
+  /// like `level.ron`, without the leading dot
+  const EXTENSIONS: &'static [&'static str];
+
+  /// Assets that start loading together with this one
+  fn dependencies(&self) -> Vec<AssetPath<'static>> {
+    Vec::new()
+  }
+}
+
+pub struct RonAssetLoader<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for RonAssetLoader<T> {
+  fn default() -> Self {
+    Self(PhantomData)
+  }
+}
+
+impl<T: RonAsset> AssetLoader for RonAssetLoader<T> {
+  fn load<'a>(
+    &'a self,
+    bytes: &'a [u8],
+    load_context: &'a mut LoadContext,
+  ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+    Box::pin(async move {
+      let asset: T = ron::de::from_bytes(bytes)?;
+      let dependencies = asset.dependencies();
+      load_context.set_default_asset(LoadedAsset::new(asset).with_dependencies(dependencies));
+      Ok(())
+    })
+  }
+
+  fn extensions(&self) -> &[&str] {
+    T::EXTENSIONS
+  }
+}
+
+pub trait RonAssetExtensions {
+  /// Registers the asset type together with its loader
+  fn add_ron_asset<T: RonAsset>(&mut self) -> &mut Self;
+}
+
+impl RonAssetExtensions for App {
+  fn add_ron_asset<T: RonAsset>(&mut self) -> &mut Self {
+    self
+      .add_asset::<T>()
+      .init_asset_loader::<RonAssetLoader<T>>()
+  }
+}
@@ -1,17 +1,240 @@
-use bevy::prelude::*;
+use bevy::{math::Vec3Swizzles, prelude::*};
+use serde::{Deserialize, Serialize};
 
-#[derive(Component)]
+/// moving further than this away from the heading counts as moving backwards
+const REVERSE_ANGLE: f32 = 3.0 * std::f32::consts::FRAC_PI_4;
+
+/// Flight model shared by all ships, it only turns input into forces and leaves applying them to
+/// whatever physics the game uses. Ships fly in the XZ plane and turn around Y.
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Spaceship {
-  velocity: Vec3,
-  mass: f32,
-  net_forces: Vec3,
+  /// force along the heading at full input
+  pub max_thrust: f32,
+  pub reverse_thrust: f32,
+  /// force against the forward velocity while moving against the heading
+  pub brake_thrust: f32,
+  /// below this forward speed braking is done and the reverse mode takes over
+  pub brake_speed: f32,
+  /// torque per radian between the heading and the input direction
+  pub turn_rate: f32,
+  /// force per unit of velocity, on top of any damping the physics engine applies
+  pub drag: f32,
+  /// torque per unit of angular velocity
+  pub angular_drag: f32,
+  pub assist: FlightAssist,
+  pub reverse_mode: ReverseMode,
+}
+
+impl Default for Spaceship {
+  fn default() -> Self {
+    Self {
+      max_thrust: 120000.0,
+      reverse_thrust: 60000.0,
+      brake_thrust: 180000.0,
+      brake_speed: 5.0,
+      turn_rate: 120000.0,
+      drag: 0.0,
+      angular_drag: 0.0,
+      assist: FlightAssist::Off,
+      reverse_mode: ReverseMode::Thrust,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FlightAssist {
+  /// the ship drifts freely
+  Off,
+  /// cancels sideways drift with this force per unit of sideways velocity
+  Coupled(f32),
+}
+
+/// What moving against the heading does once the ship has stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReverseMode {
+  /// back up without turning
+  Thrust,
+  /// turn around on the spot to face the new direction
+  TurnInPlace,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ShipState {
+  /// direction the ship points at
+  pub heading: Vec3,
+  pub velocity: Vec3,
+  pub angular_velocity: Vec3,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ShipForces {
+  pub force: Vec3,
+  pub torque: Vec3,
+}
+
+impl Spaceship {
+  /// Forces for one step, `input` is the direction to fly in and its length the throttle, zero
+  /// while there is no input
+  pub fn forces(&self, state: &ShipState, input: Vec3) -> ShipForces {
+    let heading = state.heading.xz().normalize_or_zero();
+    let forward = Vec3::new(heading.x, 0.0, heading.y);
+    let mut out = ShipForces::default();
+
+    let throttle = input.xz().length().min(1.0);
+    if throttle > 0.0 {
+      let error = heading_error(forward, input);
+      if error.abs() > REVERSE_ANGLE {
+        out = self.reverse(state, forward, error, throttle);
+      } else {
+        out.force = forward * self.max_thrust * throttle;
+        out.torque = Vec3::Y * self.turn_rate * error;
+      }
+    }
+
+    out.force -= state.velocity * self.drag;
+    out.torque -= state.angular_velocity * self.angular_drag;
+
+    if let FlightAssist::Coupled(strength) = self.assist {
+      let sideways = state.velocity - forward * state.velocity.dot(forward);
+      out.force -= sideways * strength;
+    }
+
+    out
+  }
+
+  fn reverse(&self, state: &ShipState, forward: Vec3, error: f32, throttle: f32) -> ShipForces {
+    let mut out = ShipForces::default();
+    // brake first
+    if state.velocity.dot(forward) > self.brake_speed {
+      out.force = -forward * self.brake_thrust;
+    } else if self.reverse_mode == ReverseMode::Thrust {
+      out.force = -forward * self.reverse_thrust * throttle;
+    }
+    if self.reverse_mode == ReverseMode::TurnInPlace {
+      out.torque = Vec3::Y * self.turn_rate * error;
+    }
+    out
+  }
+}
+
+/// Signed angle in radians from the input direction to the heading, both projected onto XZ
+pub fn heading_error(heading: Vec3, input: Vec3) -> f32 {
+  let (heading, input) = (heading.xz(), input.xz());
+  if heading == Vec2::ZERO || input == Vec2::ZERO {
+    return 0.0;
+  }
+  input.angle_between(heading)
 }
 
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn still() -> ShipState {
+    ShipState {
+      heading: Vec3::Z,
+      ..default()
+    }
+  }
+
+  fn moving(velocity: Vec3) -> ShipState {
+    ShipState {
+      velocity,
+      ..still()
+    }
+  }
+
+  #[test]
+  fn thrusts_along_heading() {
+    let ship = Spaceship::default();
+    let forces = ship.forces(&still(), Vec3::Z * 0.5);
+    assert!(forces.force.abs_diff_eq(Vec3::Z * ship.max_thrust * 0.5, 1e-3));
+    assert_eq!(forces.torque, Vec3::ZERO);
+  }
 
+  #[test]
+  fn no_input_no_forces() {
+    let forces = Spaceship::default().forces(&moving(Vec3::Z * 10.0), Vec3::ZERO);
+    assert_eq!(forces, ShipForces::default());
+  }
 
-// pub fn move_ships(mut qry: Query<(&mut Transform, &mut Spaceship)>) {
-//   for (mut transform, mut ship) in qry.iter_mut() {
-//     let net_force = if ship.forces.length() < 0.0001 { ship.dampener_strength }
-//     let acc = ship.forces
-//   }
-// }
\ No newline at end of file
+  #[test]
+  fn brakes_before_reversing() {
+    let ship = Spaceship::default();
+    let forces = ship.forces(&moving(Vec3::Z * 20.0), Vec3::NEG_Z);
+    assert!(forces.force.abs_diff_eq(Vec3::NEG_Z * ship.brake_thrust, 1e-3));
+    assert_eq!(forces.torque, Vec3::ZERO);
+  }
+
+  #[test]
+  fn reverses_once_stopped() {
+    let ship = Spaceship::default();
+    let forces = ship.forces(&still(), Vec3::NEG_Z);
+    assert!(forces.force.abs_diff_eq(Vec3::NEG_Z * ship.reverse_thrust, 1e-3));
+    assert_eq!(forces.torque, Vec3::ZERO);
+  }
+
+  #[test]
+  fn turns_in_place_once_stopped() {
+    let ship = Spaceship {
+      reverse_mode: ReverseMode::TurnInPlace,
+      ..default()
+    };
+    let forces = ship.forces(&still(), Vec3::new(0.1, 0.0, -1.0));
+    assert_eq!(forces.force, Vec3::ZERO);
+    assert!(forces.torque.y > 0.0);
+  }
+
+  #[test]
+  fn turns_towards_input() {
+    let ship = Spaceship::default();
+    assert!(ship.forces(&still(), Vec3::X).torque.y > 0.0);
+    assert!(ship.forces(&still(), Vec3::NEG_X).torque.y < 0.0);
+    // positive yaw turns the heading towards the input
+    let turned = Quat::from_rotation_y(0.1) * Vec3::Z;
+    assert!(heading_error(turned, Vec3::X) < heading_error(Vec3::Z, Vec3::X));
+  }
+
+  #[test]
+  fn drag_slows_down() {
+    let ship = Spaceship {
+      drag: 0.5,
+      angular_drag: 0.5,
+      ..default()
+    };
+    let mut state = ShipState {
+      angular_velocity: Vec3::Y * 2.0,
+      ..moving(Vec3::new(3.0, 0.0, 4.0))
+    };
+    let dt = 0.1;
+    for _ in 0..100 {
+      let forces = ship.forces(&state, Vec3::ZERO);
+      let next_velocity = state.velocity + forces.force * dt;
+      let next_angular = state.angular_velocity + forces.torque * dt;
+      assert!(next_velocity.length() < state.velocity.length());
+      assert!(next_angular.length() < state.angular_velocity.length());
+      // drag never pushes backwards
+      assert!(next_velocity.dot(state.velocity) > 0.0);
+      state.velocity = next_velocity;
+      state.angular_velocity = next_angular;
+    }
+    assert!(state.velocity.length() < 0.1);
+  }
+
+  #[test]
+  fn assist_cancels_sideways_drift() {
+    let velocity = Vec3::new(5.0, 0.0, 10.0);
+    let off = Spaceship::default().forces(&moving(velocity), Vec3::ZERO);
+    assert_eq!(off.force, Vec3::ZERO);
+
+    let coupled = Spaceship {
+      assist: FlightAssist::Coupled(2.0),
+      ..default()
+    };
+    let forces = coupled.forces(&moving(velocity), Vec3::ZERO);
+    assert!(forces.force.abs_diff_eq(Vec3::NEG_X * 10.0, 1e-3));
+    // keeps the forward speed
+    assert_eq!(forces.force.dot(Vec3::Z), 0.0);
+  }
+}
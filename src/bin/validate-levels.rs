@@ -0,0 +1,258 @@
+//! Checks every level file without starting the game
+//!
+//! ```sh
+//! $ cargo run --bin validate-levels [assets dir]
+//! ```
+
+use std::{
+  collections::BTreeMap,
+  path::{Path, PathBuf},
+  process::ExitCode,
+};
+
+use bevy::{math::Vec3Swizzles, prelude::*};
+use serde::Deserialize;
+use utils::level::{LevelAsset, ObjectiveDef};
+
+const LEVEL_EXTENSION: &str = ".level.ron";
+/// the game builds player ships from this file
+const PLAYER_SETTINGS: &str = "default.player.ron";
+
+/// The part of the player settings that matters for level layout
+#[derive(Deserialize)]
+struct PlayerShip {
+  collider_radius: f32,
+}
+
+#[derive(Default)]
+struct Report {
+  errors: Vec<String>,
+  warnings: Vec<String>,
+}
+
+impl Report {
+  fn error(&mut self, file: &str, msg: String) {
+    self.errors.push(format!("{}: {}", file, msg));
+  }
+
+  fn warn(&mut self, file: &str, msg: String) {
+    self.warnings.push(format!("{}: {}", file, msg));
+  }
+}
+
+fn main() -> ExitCode {
+  let assets = std::env::args()
+    .nth(1)
+    .map(PathBuf::from)
+    .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"));
+
+  let mut report = Report::default();
+  // overlap checks need the ship size
+  let Some(ship_radius) = read_ship_radius(&assets, &mut report) else {
+    for e in report.errors.iter() {
+      println!("error: {}", e);
+    }
+    return ExitCode::FAILURE;
+  };
+  let levels = read_levels(&assets, &mut report);
+
+  // the game moves on to level id + 1 and ends at the first id without a file, so a gap hides
+  // every level after it
+  if let Some(max_id) = levels.keys().max() {
+    for id in 0..*max_id {
+      if !levels.contains_key(&id) {
+        report.error(&format!("{}{}", id, LEVEL_EXTENSION), "missing level id".to_string());
+      }
+    }
+  }
+
+  for (id, level) in levels.iter() {
+    let file = format!("{}{}", id, LEVEL_EXTENSION);
+    validate_level(&file, level, &assets, ship_radius, &mut report);
+  }
+
+  for w in report.warnings.iter() {
+    println!("warning: {}", w);
+  }
+  for e in report.errors.iter() {
+    println!("error: {}", e);
+  }
+  println!(
+    "checked {} levels, {} errors, {} warnings",
+    levels.len(),
+    report.errors.len(),
+    report.warnings.len()
+  );
+
+  if report.errors.is_empty() {
+    ExitCode::SUCCESS
+  } else {
+    ExitCode::FAILURE
+  }
+}
+
+fn read_ship_radius(assets: &Path, report: &mut Report) -> Option<f32> {
+  match std::fs::read(assets.join(PLAYER_SETTINGS))
+    .map_err(|e| e.to_string())
+    .and_then(|bytes| ron::de::from_bytes::<PlayerShip>(&bytes).map_err(|e| e.to_string()))
+  {
+    Ok(ship) => Some(ship.collider_radius),
+    Err(e) => {
+      report.error(PLAYER_SETTINGS, e);
+      None
+    }
+  }
+}
+
+fn read_levels(assets: &Path, report: &mut Report) -> BTreeMap<u64, LevelAsset> {
+  let mut levels = BTreeMap::new();
+  let dir = assets.join("levels");
+  let entries = match std::fs::read_dir(&dir) {
+    Ok(entries) => entries,
+    Err(e) => {
+      report.error(&dir.display().to_string(), e.to_string());
+      return levels;
+    }
+  };
+
+  for entry in entries.flatten() {
+    let file = entry.file_name().to_string_lossy().to_string();
+    let Some(stem) = file.strip_suffix(LEVEL_EXTENSION) else {
+      continue;
+    };
+    let Ok(id) = stem.parse::<u64>() else {
+      report.error(&file, "file name is not a level id".to_string());
+      continue;
+    };
+
+    match std::fs::read(entry.path())
+      .map_err(|e| e.to_string())
+      .and_then(|bytes| ron::de::from_bytes::<LevelAsset>(&bytes).map_err(|e| e.to_string()))
+    {
+      Ok(level) => {
+        levels.insert(id, level);
+      }
+      Err(e) => report.error(&file, e),
+    }
+  }
+
+  levels
+}
+
+fn validate_level(
+  file: &str,
+  level: &LevelAsset,
+  assets: &Path,
+  ship_radius: f32,
+  report: &mut Report,
+) {
+  for path in level.asset_paths() {
+    // strip labels like #Scene0
+    let file_path = path.split('#').next().unwrap_or(path);
+    if !assets.join(file_path).is_file() {
+      report.error(file, format!("unknown asset {}", path));
+    }
+  }
+
+  if level.spawn_points.is_empty() {
+    report.error(file, "no spawn points".to_string());
+  }
+
+  let bounds = level.bounds;
+  let in_bounds = |p: Vec3| p.xz().cmpge(bounds.min).all() && p.xz().cmple(bounds.max).all();
+
+  for (i, spawn) in level.spawn_points.iter().enumerate() {
+    if !in_bounds(spawn.position) {
+      report.error(file, format!("spawn point {} is out of bounds", i));
+    }
+    for (j, other) in level.spawn_points.iter().enumerate().skip(i + 1) {
+      if spawn.position.distance(other.position) < ship_radius * 2.0 {
+        report.error(file, format!("spawn points {} and {} overlap", i, j));
+      }
+    }
+    for prop in level.props.iter() {
+      if let Some(radius) = prop.collider_radius {
+        if spawn.position.distance(prop.position) < radius * prop.scale + ship_radius {
+          report.error(file, format!("spawn point {} overlaps prop {}", i, prop.model));
+        }
+      }
+    }
+    for enemy in level.enemies.iter() {
+      if spawn.position.distance(enemy.position) < enemy.collider_radius + ship_radius {
+        report.error(file, format!("spawn point {} overlaps enemy {}", i, enemy.kind));
+      }
+    }
+    for hazard in level.hazards.iter() {
+      if spawn.position.distance(hazard.position) < hazard.radius + ship_radius {
+        report.warn(file, format!("spawn point {} is inside hazard {}", i, hazard.kind));
+      }
+    }
+  }
+
+  for (i, pickup) in level.pickups.iter().enumerate() {
+    if !in_bounds(pickup.position) {
+      report.error(file, format!("pickup {} is out of bounds", i));
+    }
+  }
+  for enemy in level.enemies.iter() {
+    for drop in enemy.drops.iter() {
+      if !(0.0..=1.0).contains(&drop.chance) {
+        report.error(file, format!("enemy {} drop chance {} is not 0..1", enemy.kind, drop.chance));
+      }
+    }
+  }
+
+  for (i, objective) in level.objectives.iter().enumerate() {
+    match objective {
+      ObjectiveDef::DestroyTargets { count, kind } => {
+        let available = level
+          .enemies
+          .iter()
+          .filter(|e| kind.as_ref().map_or(true, |k| *k == e.kind))
+          .count();
+        if available < *count as usize {
+          report.error(
+            file,
+            format!("objective {} needs {} targets but only {} exist", i, count, available),
+          );
+        }
+      }
+      ObjectiveDef::ReachWaypoint { position, radius } => {
+        if !in_bounds(*position) {
+          report.error(file, format!("objective {} waypoint is out of bounds", i));
+        }
+        let blocked = level.props.iter().any(|p| {
+          p.collider_radius
+            .map_or(false, |r| p.position.distance(*position) + radius <= r * p.scale)
+        });
+        if blocked {
+          report.error(file, format!("objective {} waypoint is inside a prop", i));
+        }
+      }
+      ObjectiveDef::Survive { seconds } => {
+        if *seconds <= 0.0 {
+          report.warn(file, format!("objective {} completes immediately", i));
+        }
+      }
+      ObjectiveDef::Protect { tag } => {
+        let tagged = level
+          .props
+          .iter()
+          .filter_map(|p| p.tag.as_ref())
+          .chain(level.enemies.iter().filter_map(|e| e.tag.as_ref()))
+          .any(|t| t == tag);
+        if !tagged {
+          report.error(file, format!("objective {} protects missing tag {}", i, tag));
+        }
+      }
+    }
+  }
+
+  let protect_only = level
+    .objectives
+    .iter()
+    .all(|o| matches!(o, ObjectiveDef::Protect { .. }));
+  if protect_only && !level.objectives.is_empty() {
+    report.error(file, "level can never be won, it only has protect objectives".to_string());
+  }
+}
@@ -0,0 +1,125 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use utils::loadout::BoostDef;
+
+use super::ship::{fly_ships, physics_dt};
+
+pub struct BoostPlugin;
+impl Plugin for BoostPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .add_event::<BoostCommand>()
+      .add_event::<BoostEvent>()
+      .add_system(handle_cmd)
+      .add_system(update_boosts.after(handle_cmd).after(fly_ships));
+  }
+}
+
+#[derive(Component, Clone)]
+pub struct Boost {
+  pub energy: f32,
+  pub max_energy: f32,
+  /// energy spent per boost
+  pub cost: f32,
+  /// energy regained per second while not boosting
+  pub recharge_rate: f32,
+  /// extra force along the heading while boosting
+  pub force: f32,
+  /// seconds a boost lasts
+  pub duration: f32,
+  /// seconds after a boost before the next one can start
+  pub cooldown: f32,
+  remaining: f32,
+  cooldown_left: f32,
+}
+
+impl Default for Boost {
+  fn default() -> Self {
+    BoostDef::default().into()
+  }
+}
+
+impl From<BoostDef> for Boost {
+  fn from(def: BoostDef) -> Self {
+    Self {
+      energy: def.max_energy,
+      max_energy: def.max_energy,
+      cost: def.cost,
+      recharge_rate: def.recharge_rate,
+      force: def.force,
+      duration: def.duration,
+      cooldown: def.cooldown,
+      remaining: 0.0,
+      cooldown_left: 0.0,
+    }
+  }
+}
+
+impl Boost {
+  pub fn active(&self) -> bool {
+    self.remaining > 0.0
+  }
+
+  pub fn ready(&self) -> bool {
+    !self.active() && self.cooldown_left <= 0.0 && self.energy >= self.cost
+  }
+}
+
+/// Starts a boost if the ship has one ready
+#[derive(Debug)]
+pub struct BoostCommand(pub Entity);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoostEvent {
+  Started(Entity),
+  Ended(Entity),
+  /// the cooldown is over
+  Ready(Entity),
+}
+
+fn handle_cmd(
+  mut events: EventReader<BoostCommand>,
+  mut qry: Query<&mut Boost>,
+  mut boost_events: EventWriter<BoostEvent>,
+) {
+  for evt in events.iter() {
+    let Ok(mut boost) = qry.get_mut(evt.0) else {
+      continue;
+    };
+    if boost.ready() {
+      boost.energy -= boost.cost;
+      boost.remaining = boost.duration;
+      boost_events.send(BoostEvent::Started(evt.0));
+    }
+  }
+}
+
+/// Adds the boost on top of the impulse set by [`fly_ships`]
+pub fn update_boosts(
+  mut qry: Query<(Entity, &mut Boost, &Transform, &mut ExternalImpulse)>,
+  mut events: EventWriter<BoostEvent>,
+  config: Res<RapierConfiguration>,
+  time: Res<Time>,
+) {
+  let dt = physics_dt(&config, &time);
+  for (ship, mut boost, transform, mut impulse) in qry.iter_mut() {
+    if boost.active() {
+      let heading = (transform.rotation * Vec3::Z).reject_from(Vec3::Y).normalize_or_zero();
+      impulse.impulse += heading * boost.force * dt;
+      boost.remaining -= dt;
+      if !boost.active() {
+        boost.cooldown_left = boost.cooldown;
+        events.send(BoostEvent::Ended(ship));
+      }
+      continue;
+    }
+
+    boost.energy = (boost.energy + boost.recharge_rate * dt).min(boost.max_energy);
+    if boost.cooldown_left > 0.0 {
+      boost.cooldown_left -= dt;
+      if boost.cooldown_left <= 0.0 {
+        events.send(BoostEvent::Ready(ship));
+      }
+    }
+  }
+}
@@ -1,5 +1,5 @@
 use bevy::prelude::*;
-use bevy_rapier3d::prelude::{ExternalImpulse, Velocity};
+use bevy_rapier3d::prelude::Velocity;
 
 #[derive(Component, Default)]
 pub struct PidCamera {
@@ -7,8 +7,17 @@ pub struct PidCamera {
   pub integrated_error: Vec3,
   pub last_error: Vec3,
   pub offset: Option<Vec3>,
+  /// field of view before framing, taken from the first frame
+  pub fov: Option<f32>,
 }
 
+/// room kept around the outermost target when framing several of them
+const FRAMING_MARGIN: f32 = 30.0;
+/// the field of view is never widened past this
+const MAX_FOV: f32 = 1.8;
+/// how quickly the field of view follows the targets
+const FOV_SPEED: f32 = 2.0;
+
 #[derive(Component)]
 pub struct PidCameraTarget;
 
@@ -19,16 +28,24 @@ impl Plugin for PidCameraPlugin {
   }
 }
 
+/// Follows the center of all targets and widens the field of view until all of them fit
 fn follow_target(
   qry_transform: Query<&Transform, (With<PidCameraTarget>, Without<Camera>)>,
   mut qry_camera: Query<
-    (&mut Transform, &mut Velocity, &mut PidCamera),
+    (&Transform, &mut Velocity, &mut PidCamera, Option<&mut Projection>),
     (Without<PidCameraTarget>, With<Camera>),
   >,
   time: Res<Time>,
 ) {
-  for (mut cam_transform, mut velocity, mut pid) in qry_camera.iter_mut() {
-    if let Ok(target_transform) = qry_transform.get_single() {
+  let count = qry_transform.iter().len();
+  let center = qry_transform.iter().map(|t| t.translation).sum::<Vec3>() / count.max(1) as f32;
+  let spread = qry_transform
+    .iter()
+    .map(|t| t.translation.distance(center))
+    .fold(0.0, f32::max);
+
+  for (cam_transform, mut velocity, mut pid, projection) in qry_camera.iter_mut() {
+    if count > 0 {
       // TODO: interpolate
 
       let offset = if let Some(o) = pid.offset {
@@ -50,13 +67,13 @@ fn follow_target(
           "new transform {:?}, {:?}, {:?}",
           cam_transform.translation,
           intersection,
-          target_transform.translation - o
+          center - o
         );
         info!("direction and magnituted: {:?} {:?}", direction, t);
         o
       };
 
-      let error = target_transform.translation - offset - cam_transform.translation;
+      let error = center - offset - cam_transform.translation;
 
       pid.integrated_error += error * time.delta_seconds();
       pid.last_error = error * time.delta_seconds();
@@ -69,6 +86,13 @@ fn follow_target(
       velocity.linvel = p + i + d;
 
       pid.last_error = error;
+
+      if let Some(Projection::Perspective(perspective)) = projection.map(|p| p.into_inner()) {
+        let base_fov = *pid.fov.get_or_insert(perspective.fov);
+        let needed = 2.0 * ((spread + FRAMING_MARGIN) / offset.length()).atan();
+        let fov = needed.min(MAX_FOV).max(base_fov);
+        perspective.fov += (fov - perspective.fov) * (FOV_SPEED * time.delta_seconds()).min(1.0);
+      }
     }
   }
 }
@@ -0,0 +1,537 @@
+use bevy::{asset::FileAssetIo, prelude::*};
+use utils::{despawn_screen, vfx::ToonMaterial};
+
+use super::{
+  level::{level_path, EnemyDef, HazardDef, LevelAsset, LevelState, PropDef, SpawnPointDef},
+  player::{
+    crosshair::Crosshair,
+    input::{Action, ActionInput, InputMap},
+    PlayerId,
+  },
+};
+
+const TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+const PICK_RADIUS: f32 = 25.0;
+const ROTATE_SPEED: f32 = 2.0;
+
+pub trait EditorExtensions {
+  fn add_level_editor<T: States>(&mut self, show_on_state: T, play_state: T, exit_state: T)
+    -> &mut Self;
+}
+
+impl EditorExtensions for App {
+  fn add_level_editor<T: States>(
+    &mut self,
+    show_on_state: T,
+    play_state: T,
+    exit_state: T,
+  ) -> &mut Self {
+    self
+      .insert_resource(EditorStates {
+        editor: show_on_state.clone(),
+        play: play_state,
+        exit: exit_state,
+      })
+      .init_resource::<EditorPalette>()
+      .init_resource::<EditorAssets>()
+      .add_system(toggle_editor::<T>)
+      .add_systems((
+        editor_setup.in_schedule(OnEnter(show_on_state.clone())),
+        despawn_screen::<OnEditor>.in_schedule(OnExit(show_on_state.clone())),
+        editor_cleanup.in_schedule(OnExit(show_on_state.clone())),
+      ))
+      .add_systems(
+        (
+          populate_document,
+          select_palette,
+          edit_items.after(populate_document),
+          save_document.after(edit_items),
+          update_editor_ui.after(edit_items),
+        )
+          .in_set(OnUpdate(show_on_state)),
+      )
+  }
+}
+
+#[derive(Resource)]
+struct EditorStates<T> {
+  editor: T,
+  play: T,
+  exit: T,
+}
+
+#[derive(Component)]
+struct OnEditor;
+
+#[derive(Component)]
+struct EditorText;
+
+#[derive(Clone, Debug)]
+enum EditorItemDef {
+  SpawnPoint(SpawnPointDef),
+  Prop(PropDef),
+  Enemy(EnemyDef),
+  Hazard(HazardDef),
+}
+
+impl EditorItemDef {
+  fn position(&self) -> Vec3 {
+    match self {
+      EditorItemDef::SpawnPoint(d) => d.position,
+      EditorItemDef::Prop(d) => d.position,
+      EditorItemDef::Enemy(d) => d.position,
+      EditorItemDef::Hazard(d) => d.position,
+    }
+  }
+
+  fn heading(&self) -> f32 {
+    match self {
+      EditorItemDef::SpawnPoint(d) => d.heading,
+      EditorItemDef::Prop(d) => d.heading,
+      EditorItemDef::Enemy(d) => d.heading,
+      EditorItemDef::Hazard(_) => 0.0,
+    }
+  }
+
+  fn with_transform(&self, transform: &Transform) -> Self {
+    let position = transform.translation;
+    let heading = transform.rotation.to_euler(EulerRot::YXZ).0;
+    match self.clone() {
+      EditorItemDef::SpawnPoint(_) => {
+        EditorItemDef::SpawnPoint(SpawnPointDef { position, heading })
+      }
+      EditorItemDef::Prop(d) => EditorItemDef::Prop(PropDef {
+        position,
+        heading,
+        ..d
+      }),
+      EditorItemDef::Enemy(d) => EditorItemDef::Enemy(EnemyDef {
+        position,
+        heading,
+        ..d
+      }),
+      EditorItemDef::Hazard(d) => EditorItemDef::Hazard(HazardDef { position, ..d }),
+    }
+  }
+
+  fn label(&self) -> String {
+    match self {
+      EditorItemDef::SpawnPoint(_) => "spawn point".to_string(),
+      EditorItemDef::Prop(d) => format!("prop {}", d.model),
+      EditorItemDef::Enemy(d) => format!("enemy {}", d.kind),
+      EditorItemDef::Hazard(d) => format!("hazard {}", d.kind),
+    }
+  }
+}
+
+#[derive(Component)]
+struct EditorItem(EditorItemDef);
+
+#[derive(Component)]
+struct Selected;
+
+#[derive(Component)]
+struct Grabbed;
+
+#[derive(Resource)]
+struct EditorPalette {
+  items: Vec<EditorItemDef>,
+  current: usize,
+}
+
+impl Default for EditorPalette {
+  fn default() -> Self {
+    Self {
+      items: vec![
+        EditorItemDef::SpawnPoint(SpawnPointDef {
+          position: Vec3::ZERO,
+          heading: 0.0,
+        }),
+        EditorItemDef::Prop(PropDef {
+          model: "ship.gltf#Scene0".to_string(),
+          position: Vec3::ZERO,
+          heading: 0.0,
+          scale: 3.0,
+          collider_radius: Some(5.0),
+          tag: None,
+          health: None,
+        }),
+        EditorItemDef::Enemy(EnemyDef {
+          kind: "drone".to_string(),
+          model: "ship.gltf#Scene0".to_string(),
+          position: Vec3::ZERO,
+          heading: 0.0,
+          tag: None,
+          health: 30.0,
+          collider_radius: 5.0,
+          drops: Vec::new(),
+        }),
+        EditorItemDef::Hazard(HazardDef {
+          kind: "radiation".to_string(),
+          position: Vec3::ZERO,
+          radius: 30.0,
+        }),
+      ],
+      current: 0,
+    }
+  }
+}
+
+/// Meshes for items that have no model of their own
+#[derive(Resource)]
+struct EditorAssets {
+  marker: Handle<Mesh>,
+  sphere: Handle<Mesh>,
+  material: Handle<ToonMaterial>,
+}
+
+impl FromWorld for EditorAssets {
+  fn from_world(world: &mut World) -> Self {
+    let mut meshes = world.resource_mut::<Assets<Mesh>>();
+    let marker = meshes.add(shape::Cube { size: 5.0 }.into());
+    let sphere = meshes.add(
+      shape::UVSphere {
+        radius: 1.0,
+        ..default()
+      }
+      .into(),
+    );
+    let material = world.resource_mut::<Assets<ToonMaterial>>().add(ToonMaterial {
+      color: Color::rgb(0.9, 0.3, 0.9).into(),
+      color_texture: None,
+      alpha_mode: AlphaMode::Opaque,
+    });
+    Self {
+      marker,
+      sphere,
+      material,
+    }
+  }
+}
+
+/// The level being edited, `level` is filled in once the asset is available
+#[derive(Resource)]
+struct EditorDocument {
+  level_id: u64,
+  handle: Handle<LevelAsset>,
+  level: Option<LevelAsset>,
+}
+
+fn toggle_editor<T: States>(
+  input: ActionInput,
+  state: Res<State<T>>,
+  states: Res<EditorStates<T>>,
+  mut next_state: ResMut<NextState<T>>,
+) {
+  if !input.just_pressed(Action::ToggleEditor) {
+    return;
+  }
+
+  if state.0 == states.play {
+    next_state.set(states.editor.clone());
+  } else if state.0 == states.editor {
+    // go through the exit state so the saved level gets loaded again
+    next_state.set(states.exit.clone());
+  }
+}
+
+fn editor_setup(
+  mut cmd: Commands,
+  level_state: Res<LevelState>,
+  asset_server: Res<AssetServer>,
+) {
+  // the level is unloaded when leaving the play state, the editor works on its own copy
+  let Some(level_id) = level_state.level_id() else {
+    warn!("no level to edit");
+    return;
+  };
+
+  cmd.insert_resource(EditorDocument {
+    level_id,
+    handle: asset_server.load(level_path(level_id)),
+    level: None,
+  });
+
+  cmd.spawn((
+    TextBundle::from_sections([
+      TextSection::new(
+        "",
+        TextStyle {
+          font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+          font_size: 20.0,
+          color: TEXT_COLOR,
+        },
+      ),
+    ])
+    .with_style(Style {
+      position_type: PositionType::Absolute,
+      position: UiRect {
+        left: Val::Px(10.0),
+        top: Val::Px(10.0),
+        ..default()
+      },
+      ..default()
+    }),
+    EditorText,
+    OnEditor,
+  ));
+}
+
+fn editor_cleanup(mut cmd: Commands) {
+  cmd.remove_resource::<EditorDocument>();
+}
+
+fn populate_document(
+  mut cmd: Commands,
+  document: Option<ResMut<EditorDocument>>,
+  levels: Res<Assets<LevelAsset>>,
+  editor_assets: Res<EditorAssets>,
+  asset_server: Res<AssetServer>,
+) {
+  let Some(mut document) = document else {
+    return;
+  };
+  if document.level.is_some() {
+    return;
+  }
+  let Some(level) = levels.get(&document.handle) else {
+    return;
+  };
+
+  let items = level
+    .spawn_points
+    .iter()
+    .cloned()
+    .map(EditorItemDef::SpawnPoint)
+    .chain(level.props.iter().cloned().map(EditorItemDef::Prop))
+    .chain(level.enemies.iter().cloned().map(EditorItemDef::Enemy))
+    .chain(level.hazards.iter().cloned().map(EditorItemDef::Hazard));
+  for item in items {
+    spawn_item(&mut cmd, item, &editor_assets, &asset_server);
+  }
+
+  info!("editing level {}", document.level_id);
+  document.level = Some(level.clone());
+}
+
+fn spawn_item(
+  cmd: &mut Commands,
+  item: EditorItemDef,
+  editor_assets: &EditorAssets,
+  asset_server: &AssetServer,
+) -> Entity {
+  let transform = Transform::from_translation(item.position())
+    .with_rotation(Quat::from_rotation_y(item.heading()));
+
+  let mut entity = match &item {
+    EditorItemDef::Prop(PropDef { model, scale, .. }) => cmd.spawn(SceneBundle {
+      scene: asset_server.load(model.as_str()),
+      transform: transform.with_scale(Vec3::splat(*scale)),
+      ..default()
+    }),
+    EditorItemDef::Enemy(EnemyDef { model, .. }) => cmd.spawn(SceneBundle {
+      scene: asset_server.load(model.as_str()),
+      transform,
+      ..default()
+    }),
+    EditorItemDef::SpawnPoint(_) => cmd.spawn(MaterialMeshBundle {
+      mesh: editor_assets.marker.clone(),
+      material: editor_assets.material.clone(),
+      transform,
+      ..default()
+    }),
+    EditorItemDef::Hazard(HazardDef { radius, .. }) => cmd.spawn(MaterialMeshBundle {
+      mesh: editor_assets.sphere.clone(),
+      material: editor_assets.material.clone(),
+      transform: transform.with_scale(Vec3::splat(*radius)),
+      ..default()
+    }),
+  };
+
+  entity.insert((EditorItem(item), OnEditor)).id()
+}
+
+fn select_palette(input: ActionInput, mut palette: ResMut<EditorPalette>) {
+  for i in 0..palette.items.len() {
+    if input.just_pressed(Action::EditorPalette(i as u8)) {
+      palette.current = i;
+    }
+  }
+}
+
+fn edit_items(
+  mut cmd: Commands,
+  palette: Res<EditorPalette>,
+  editor_assets: Res<EditorAssets>,
+  asset_server: Res<AssetServer>,
+  qry_crosshair: Query<(&PlayerId, &Crosshair)>,
+  input: ActionInput,
+  mut qry_items: Query<
+    (Entity, &mut Transform, Option<&Selected>, Option<&Grabbed>),
+    With<EditorItem>,
+  >,
+  time: Res<Time>,
+) {
+  // the editor follows whoever is on the mouse
+  let Some(cursor) = qry_crosshair
+    .iter()
+    .find(|(player, _)| input.player(**player).uses_mouse())
+    .and_then(|(_, c)| c.world_pos)
+  else {
+    return;
+  };
+
+  let mut any_grabbed = false;
+  for (_, mut transform, _, grabbed) in qry_items.iter_mut() {
+    if grabbed.is_some() {
+      transform.translation = cursor;
+      any_grabbed = true;
+    }
+  }
+
+  if input.just_pressed(Action::EditorPlace) {
+    if any_grabbed {
+      // drop whatever is being moved
+      for (entity, _, _, grabbed) in qry_items.iter() {
+        if grabbed.is_some() {
+          cmd.entity(entity).remove::<Grabbed>();
+        }
+      }
+    } else if let Some(template) = palette.items.get(palette.current) {
+      for (entity, _, selected, _) in qry_items.iter() {
+        if selected.is_some() {
+          cmd.entity(entity).remove::<Selected>();
+        }
+      }
+      let item = template.with_transform(&Transform::from_translation(cursor));
+      let entity = spawn_item(&mut cmd, item, &editor_assets, &asset_server);
+      cmd.entity(entity).insert(Selected);
+    }
+  }
+
+  if input.just_pressed(Action::EditorSelect) {
+    let nearest = qry_items
+      .iter()
+      .map(|(entity, transform, _, _)| (entity, transform.translation.distance(cursor)))
+      .filter(|(_, d)| *d <= PICK_RADIUS)
+      .min_by(|a, b| a.1.total_cmp(&b.1));
+    for (entity, _, selected, _) in qry_items.iter() {
+      if selected.is_some() {
+        cmd.entity(entity).remove::<Selected>();
+      }
+    }
+    if let Some((entity, _)) = nearest {
+      cmd.entity(entity).insert(Selected);
+    }
+  }
+
+  let rotation = match (
+    input.pressed(Action::EditorRotateLeft),
+    input.pressed(Action::EditorRotateRight),
+  ) {
+    (true, false) => ROTATE_SPEED,
+    (false, true) => -ROTATE_SPEED,
+    _ => 0.0,
+  } * time.delta_seconds();
+
+  for (entity, mut transform, selected, _) in qry_items.iter_mut() {
+    if selected.is_none() {
+      continue;
+    }
+    if input.just_pressed(Action::EditorDelete) {
+      cmd.entity(entity).despawn_recursive();
+      continue;
+    }
+    if input.just_pressed(Action::EditorGrab) {
+      cmd.entity(entity).insert(Grabbed);
+    }
+    transform.rotate_y(rotation);
+  }
+}
+
+fn save_document(
+  input: ActionInput,
+  document: Option<Res<EditorDocument>>,
+  qry_items: Query<(&EditorItem, &Transform)>,
+) {
+  if !input.just_pressed(Action::EditorSave) {
+    return;
+  }
+  let Some((level_id, Some(base))) = document.as_ref().map(|d| (d.level_id, &d.level)) else {
+    warn!("nothing to save");
+    return;
+  };
+
+  let mut level = LevelAsset {
+    spawn_points: Vec::new(),
+    props: Vec::new(),
+    enemies: Vec::new(),
+    hazards: Vec::new(),
+    ..base.clone()
+  };
+  for (item, transform) in qry_items.iter() {
+    match item.0.with_transform(transform) {
+      EditorItemDef::SpawnPoint(d) => level.spawn_points.push(d),
+      EditorItemDef::Prop(d) => level.props.push(d),
+      EditorItemDef::Enemy(d) => level.enemies.push(d),
+      EditorItemDef::Hazard(d) => level.hazards.push(d),
+    }
+  }
+
+  let path = FileAssetIo::get_base_path()
+    .join("assets")
+    .join(level_path(level_id));
+  let result = ron::ser::to_string_pretty(&level, ron::ser::PrettyConfig::default())
+    .map_err(|e| e.to_string())
+    .and_then(|s| std::fs::write(&path, s).map_err(|e| e.to_string()));
+
+  match result {
+    Ok(_) => info!("saved level {} to {:?}", level_id, path),
+    Err(e) => error!("failed to save level {}: {}", level_id, e),
+  }
+}
+
+fn update_editor_ui(
+  palette: Res<EditorPalette>,
+  qry_selected: Query<(&EditorItem, Option<&Grabbed>), With<Selected>>,
+  mut qry_text: Query<&mut Text, With<EditorText>>,
+  input_map: Res<InputMap>,
+) {
+  let Ok(mut text) = qry_text.get_single_mut() else {
+    return;
+  };
+
+  let palette_line = palette
+    .items
+    .iter()
+    .enumerate()
+    .map(|(i, item)| {
+      let marker = if i == palette.current { ">" } else { " " };
+      format!("{}{} {}", marker, i + 1, item.label())
+    })
+    .collect::<Vec<_>>()
+    .join("\n");
+  let selection = match qry_selected.get_single() {
+    Ok((item, grabbed)) => format!(
+      "selected: {}{}",
+      item.0.label(),
+      if grabbed.is_some() { " (moving)" } else { "" }
+    ),
+    Err(_) => "nothing selected".to_string(),
+  };
+
+  let key = |action| input_map.binding_name(action);
+  text.sections[0].value = format!(
+    "LEVEL EDITOR\n{}\n\n{}\n\n{} place/drop, {} select, {} move, {}/{} rotate, {} delete\n{} save, {} play",
+    palette_line,
+    selection,
+    key(Action::EditorPlace),
+    key(Action::EditorSelect),
+    key(Action::EditorGrab),
+    key(Action::EditorRotateLeft),
+    key(Action::EditorRotateRight),
+    key(Action::EditorDelete),
+    key(Action::EditorSave),
+    key(Action::ToggleEditor),
+  );
+}
@@ -0,0 +1,240 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, utils::HashSet};
+use bevy_rapier3d::prelude::*;
+
+use super::{
+  level::LevelHazard,
+  player::{PlayerCommand, PlayerComponent, PlayerId},
+  shield::{Shield, ShieldEvent},
+  ship::physics_dt,
+  weapons::Projectile,
+};
+
+/// damage per second taken inside a radiation hazard
+const RADIATION_DPS: f32 = 10.0;
+
+pub struct HealthPlugin;
+impl Plugin for HealthPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .add_event::<DamageEvent>()
+      .add_event::<Destroyed>()
+      .add_system(projectile_hits)
+      .add_system(impact_damage)
+      .add_system(hazard_damage)
+      .add_system(
+        apply_damage
+          .after(projectile_hits)
+          .after(impact_damage)
+          .after(hazard_damage),
+      )
+      .add_system(handle_destroyed.after(apply_damage))
+      .add_system(expire_invulnerability);
+  }
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Health {
+  pub current: f32,
+  pub max: f32,
+}
+
+impl Health {
+  pub fn new(max: f32) -> Self {
+    Self { current: max, max }
+  }
+}
+
+/// Ignores all damage until the timer runs out
+#[derive(Component)]
+pub struct Invulnerable(pub Timer);
+
+impl Invulnerable {
+  pub fn new(seconds: f32) -> Self {
+    Self(Timer::from_seconds(seconds, TimerMode::Once))
+  }
+}
+
+/// Entities with this take damage from hitting things, needs `ActiveEvents::CONTACT_FORCE_EVENTS`
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ImpactDamage {
+  /// contact forces below this are harmless
+  pub threshold: f32,
+  /// damage per unit of force above the threshold
+  pub per_force: f32,
+}
+
+impl Default for ImpactDamage {
+  fn default() -> Self {
+    Self {
+      threshold: 5000.0,
+      per_force: 0.002,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DamageEvent {
+  pub target: Entity,
+  pub amount: f32,
+  /// the entity that caused the damage, like the ship that fired a projectile
+  pub source: Option<Entity>,
+}
+
+/// Sent once when an entity runs out of health
+#[derive(Debug, Clone, Copy)]
+pub struct Destroyed {
+  pub entity: Entity,
+  pub by: Option<Entity>,
+}
+
+fn projectile_hits(
+  mut cmd: Commands,
+  mut collisions: EventReader<CollisionEvent>,
+  mut damage: EventWriter<DamageEvent>,
+  qry_projectile: Query<&Projectile>,
+  qry_sensor: Query<(), With<Sensor>>,
+  qry_health: Query<(), With<Health>>,
+) {
+  // a projectile touching two colliders in the same frame only hits one of them
+  let mut spent = HashSet::new();
+
+  for evt in collisions.iter() {
+    let CollisionEvent::Started(a, b, _) = evt else {
+      continue;
+    };
+    for (hit, other) in [(*a, *b), (*b, *a)] {
+      let Ok(projectile) = qry_projectile.get(hit) else {
+        continue;
+      };
+      if other == projectile.owner || qry_sensor.contains(other) || !spent.insert(hit) {
+        continue;
+      }
+      if qry_health.contains(other) {
+        damage.send(DamageEvent {
+          target: other,
+          amount: projectile.damage,
+          source: Some(projectile.owner),
+        });
+      }
+      cmd.entity(hit).despawn_recursive();
+    }
+  }
+}
+
+fn impact_damage(
+  mut contacts: EventReader<ContactForceEvent>,
+  mut damage: EventWriter<DamageEvent>,
+  qry: Query<&ImpactDamage>,
+) {
+  for evt in contacts.iter() {
+    for (target, other) in [
+      (evt.collider1, evt.collider2),
+      (evt.collider2, evt.collider1),
+    ] {
+      let Ok(impact) = qry.get(target) else {
+        continue;
+      };
+      if evt.total_force_magnitude > impact.threshold {
+        damage.send(DamageEvent {
+          target,
+          amount: (evt.total_force_magnitude - impact.threshold) * impact.per_force,
+          source: Some(other),
+        });
+      }
+    }
+  }
+}
+
+fn hazard_damage(
+  mut damage: EventWriter<DamageEvent>,
+  qry_hazard: Query<(Entity, &LevelHazard, &GlobalTransform)>,
+  qry_ship: Query<(Entity, &GlobalTransform), (With<PlayerComponent>, With<Health>)>,
+  config: Res<RapierConfiguration>,
+  time: Res<Time>,
+) {
+  let dt = physics_dt(&config, &time);
+  for (hazard, def, hazard_transform) in qry_hazard.iter() {
+    let dps = match def.kind.as_str() {
+      "radiation" => RADIATION_DPS,
+      _ => continue,
+    };
+    for (ship, transform) in qry_ship.iter() {
+      if transform.translation().distance(hazard_transform.translation()) <= def.radius {
+        damage.send(DamageEvent {
+          target: ship,
+          amount: dps * dt,
+          source: Some(hazard),
+        });
+      }
+    }
+  }
+}
+
+pub fn apply_damage(
+  mut events: EventReader<DamageEvent>,
+  mut qry: Query<(&mut Health, Option<&mut Shield>), Without<Invulnerable>>,
+  mut shield_events: EventWriter<ShieldEvent>,
+  mut destroyed: EventWriter<Destroyed>,
+) {
+  for evt in events.iter() {
+    let Ok((mut health, shield)) = qry.get_mut(evt.target) else {
+      continue;
+    };
+    // already destroyed this frame
+    if health.current <= 0.0 {
+      continue;
+    }
+
+    let mut amount = evt.amount;
+    if let Some(mut shield) = shield {
+      let through = shield.absorb(amount);
+      if through < amount {
+        shield_events.send(ShieldEvent::Absorbed {
+          ship: evt.target,
+          damage: amount - through,
+        });
+      }
+      amount = through;
+    }
+
+    health.current -= amount;
+    if health.current <= 0.0 {
+      info!("{:?} destroyed by {:?}", evt.target, evt.source);
+      destroyed.send(Destroyed {
+        entity: evt.target,
+        by: evt.source,
+      });
+    }
+  }
+}
+
+fn handle_destroyed(
+  mut cmd: Commands,
+  mut events: EventReader<Destroyed>,
+  mut player_cmd: EventWriter<PlayerCommand>,
+  qry_player: Query<&PlayerId, With<PlayerComponent>>,
+) {
+  for evt in events.iter() {
+    if let Ok(player) = qry_player.get(evt.entity) {
+      player_cmd.send(PlayerCommand::Despawn(*player));
+    } else {
+      cmd.entity(evt.entity).despawn_recursive();
+    }
+  }
+}
+
+fn expire_invulnerability(
+  mut cmd: Commands,
+  mut qry: Query<(Entity, &mut Invulnerable)>,
+  config: Res<RapierConfiguration>,
+  time: Res<Time>,
+) {
+  let dt = Duration::from_secs_f32(physics_dt(&config, &time));
+  for (entity, mut invulnerable) in qry.iter_mut() {
+    if invulnerable.0.tick(dt).just_finished() {
+      cmd.entity(entity).remove::<Invulnerable>();
+    }
+  }
+}
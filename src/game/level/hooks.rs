@@ -0,0 +1,57 @@
+use bevy::{
+  ecs::{event::ManualEventReader, schedule::ScheduleLabel},
+  prelude::*,
+};
+
+use super::LevelState;
+
+/// Sent every time the level state changes, including swaps between two active levels
+#[derive(Debug, Clone, Copy)]
+pub struct LevelTransition {
+  pub from: LevelState,
+  pub to: LevelState,
+}
+
+/// Schedules that run after the level enters the matching state, once its entities exist
+#[derive(ScheduleLabel, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LevelHook {
+  Active,
+  Unloaded,
+}
+
+pub trait LevelHooks {
+  fn on_level_active<M>(&mut self, system: impl IntoSystemConfig<M>) -> &mut Self;
+  fn on_level_unloaded<M>(&mut self, system: impl IntoSystemConfig<M>) -> &mut Self;
+}
+
+impl LevelHooks for App {
+  fn on_level_active<M>(&mut self, system: impl IntoSystemConfig<M>) -> &mut Self {
+    self.add_system(system.in_schedule(LevelHook::Active))
+  }
+
+  fn on_level_unloaded<M>(&mut self, system: impl IntoSystemConfig<M>) -> &mut Self {
+    self.add_system(system.in_schedule(LevelHook::Unloaded))
+  }
+}
+
+pub(super) fn run_level_hooks(
+  world: &mut World,
+  mut reader: Local<ManualEventReader<LevelTransition>>,
+) {
+  let hooks: Vec<LevelHook> = reader
+    .iter(world.resource::<Events<LevelTransition>>())
+    .flat_map(|t| match (t.from, t.to) {
+      // swapping to a preloaded level releases the previous one first
+      (LevelState::Active(_), LevelState::Active(_)) => {
+        vec![LevelHook::Unloaded, LevelHook::Active]
+      }
+      (_, LevelState::Active(_)) => vec![LevelHook::Active],
+      (_, LevelState::Unloaded) => vec![LevelHook::Unloaded],
+      _ => Vec::new(),
+    })
+    .collect();
+
+  for hook in hooks {
+    world.run_schedule(hook);
+  }
+}
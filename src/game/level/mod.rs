@@ -0,0 +1,516 @@
+use bevy::{asset::LoadState, prelude::*};
+use bevy_rapier3d::prelude::*;
+use utils::{ron_asset::RonAssetExtensions, vfx::Cubemap};
+
+use super::{
+  health::Health,
+  pickups::{pickup_bundle, PickupDrops},
+};
+
+pub use utils::level::{
+  level_path, EnemyDef, HazardDef, LevelAsset, LevelBounds, ObjectiveDef, PropDef, SpawnPointDef,
+};
+pub use hooks::{LevelHook, LevelHooks, LevelTransition};
+pub use sector::SectorSettings;
+pub use streaming::ChunkSettings;
+
+mod hooks;
+mod sector;
+mod streaming;
+
+#[derive(Resource, Clone)]
+pub struct LevelSettings<T> {
+  pub level_active_state: T,
+  /// generate levels from their id instead of loading level files, see `SectorSettings::first_id`
+  pub sector: Option<SectorSettings>,
+  pub chunks: ChunkSettings,
+}
+
+pub trait LevelExtensions {
+  fn add_levels<T: States>(&mut self, settings: LevelSettings<T>) -> &mut Self;
+}
+
+impl LevelExtensions for App {
+  fn add_levels<T: States>(&mut self, settings: LevelSettings<T>) -> &mut Self {
+    if let Some(sector) = settings.sector.clone() {
+      self.insert_resource(sector);
+    }
+
+    self
+      .add_ron_asset::<LevelAsset>()
+      .add_event::<LevelCommand>()
+      .add_event::<LevelUnloaded>()
+      .add_event::<LevelLoadFailed>()
+      .add_event::<LevelTransition>()
+      .init_schedule(LevelHook::Active)
+      .init_schedule(LevelHook::Unloaded)
+      .init_resource::<LevelState>()
+      .insert_resource(settings.clone())
+      .insert_resource(settings.chunks.clone())
+      .init_resource::<streaming::ChunkGround>()
+      .add_system(handle_cmd)
+      .add_system(wait_for_level::<T>.after(handle_cmd))
+      .add_system(wait_for_preload.after(handle_cmd))
+      .add_system(reload_level.after(handle_cmd))
+      .add_system(hooks::run_level_hooks.in_base_set(CoreSet::PostUpdate))
+      .add_system(streaming::stream_chunks.after(handle_cmd))
+      .add_system(unload_level.in_schedule(OnExit(settings.level_active_state.clone())))
+  }
+}
+
+/// Marks entities that belong to the current level, these are despawned when the level is unloaded
+#[derive(Component)]
+pub struct OnLevel;
+
+#[derive(Component)]
+pub struct SpawnPoint;
+
+#[derive(Component)]
+pub struct LevelEnemy {
+  pub kind: String,
+}
+
+/// Designer assigned name used to reference level entities from objectives
+#[derive(Component)]
+pub struct LevelTag(pub String);
+
+/// Objectives of the active level
+#[derive(Resource)]
+pub struct LevelObjectives(pub Vec<ObjectiveDef>);
+
+#[derive(Component)]
+pub struct LevelHazard {
+  pub kind: String,
+  pub radius: f32,
+}
+
+#[derive(Debug)]
+pub enum LevelCommand {
+  Load(u64),
+  /// load a level in the background, `Show` swaps to it once ready
+  Preload(u64),
+  Show,
+  Unload,
+}
+
+/// Only changed by the level plugin, use LevelTransition or LevelHooks to react to changes
+#[derive(Resource, Default, Copy, Clone, Debug)]
+pub enum LevelState {
+  #[default]
+  Unloaded, // nothing is loaded
+  Loading(u64), // loading a level
+  Loaded(u64),  // level is loaded but not shown
+  Active(u64),  // level is loaded and shown
+}
+
+impl LevelState {
+  pub fn level_id(&self) -> Option<u64> {
+    match *self {
+      LevelState::Unloaded => None,
+      LevelState::Loading(id) | LevelState::Loaded(id) | LevelState::Active(id) => Some(id),
+    }
+  }
+}
+
+/// Sent after all level entities and resources have been released
+#[derive(Debug)]
+pub struct LevelUnloaded(pub u64);
+
+/// Sent when `LevelCommand::Load` could not read the level, the level state is back to `Unloaded`
+#[derive(Debug)]
+pub struct LevelLoadFailed(pub u64);
+
+#[derive(Resource)]
+struct LevelHandle(Handle<LevelAsset>);
+
+/// Copy of what was spawned for the active level, used to diff against reloaded level files
+#[derive(Resource)]
+struct SpawnedLevel(LevelAsset);
+
+/// Which part of the level definition an entity was spawned from
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum LevelPart {
+  Skybox,
+  SpawnPoints,
+  Props,
+  Enemies,
+  Pickups,
+}
+
+/// Level loaded next to the current one
+#[derive(Resource)]
+pub struct PreloadedLevel {
+  pub level_id: u64,
+  pub ready: bool,
+  /// the level could not be read, usually because there is no level with this id
+  pub failed: bool,
+  handle: Handle<LevelAsset>,
+}
+
+fn handle_cmd(
+  mut cmd: Commands,
+  mut events: EventReader<LevelCommand>,
+  mut unloaded: EventWriter<LevelUnloaded>,
+  mut transitions: EventWriter<LevelTransition>,
+  mut level_state: ResMut<LevelState>,
+  qry_level: Query<Entity, With<OnLevel>>,
+  level_handle: Option<Res<LevelHandle>>,
+  preloaded: Option<Res<PreloadedLevel>>,
+  sector: Option<Res<SectorSettings>>,
+  chunk_settings: Res<ChunkSettings>,
+  mut levels: ResMut<Assets<LevelAsset>>,
+  asset_server: Res<AssetServer>,
+) {
+  for evt in events.iter() {
+    match (evt, *level_state) {
+      (LevelCommand::Load(level_id), _) => {
+        // loading continues in wait_for_level
+        let handle = load_level(*level_id, sector.as_deref(), &mut levels, &asset_server);
+        cmd.insert_resource(LevelHandle(handle));
+        set_level_state(&mut level_state, LevelState::Loading(*level_id), &mut transitions);
+      }
+      (LevelCommand::Preload(level_id), _) => {
+        // readiness is tracked in wait_for_preload
+        let handle = load_level(*level_id, sector.as_deref(), &mut levels, &asset_server);
+        cmd.insert_resource(PreloadedLevel {
+          level_id: *level_id,
+          ready: false,
+          failed: false,
+          handle,
+        });
+      }
+      (LevelCommand::Show, LevelState::Loaded(level_id)) => {
+        // only set to active if already loaded
+        let Some(level) = level_handle.as_ref().and_then(|h| levels.get(&h.0)) else {
+          warn!("level {} is loaded but the asset is missing", level_id);
+          continue;
+        };
+        spawn_level(&mut cmd, level, &chunk_settings, &asset_server);
+        set_level_state(&mut level_state, LevelState::Active(level_id), &mut transitions);
+      }
+      (LevelCommand::Show, LevelState::Active(current_id)) => {
+        // swap to the preloaded level without going through the loading state
+        let Some(next) = preloaded.as_ref().filter(|p| p.ready) else {
+          warn!("no preloaded level to show");
+          continue;
+        };
+        let Some(level) = levels.get(&next.handle) else {
+          continue;
+        };
+
+        despawn_level(&mut cmd, &qry_level);
+        unloaded.send(LevelUnloaded(current_id));
+
+        spawn_level(&mut cmd, level, &chunk_settings, &asset_server);
+        cmd.insert_resource(LevelHandle(next.handle.clone()));
+        cmd.remove_resource::<PreloadedLevel>();
+        set_level_state(&mut level_state, LevelState::Active(next.level_id), &mut transitions);
+      }
+      (LevelCommand::Unload, LevelState::Unloaded) => {}
+      (LevelCommand::Unload, _) => {
+        despawn_level(&mut cmd, &qry_level);
+        cmd.remove_resource::<LevelHandle>();
+        cmd.remove_resource::<SpawnedLevel>();
+        cmd.remove_resource::<PreloadedLevel>();
+        cmd.remove_resource::<LevelBounds>();
+        cmd.remove_resource::<streaming::LevelChunks>();
+        cmd.remove_resource::<LevelObjectives>();
+
+        if let Some(level_id) = level_state.level_id() {
+          unloaded.send(LevelUnloaded(level_id));
+        }
+        set_level_state(&mut level_state, LevelState::Unloaded, &mut transitions);
+      }
+      _ => {
+        warn!("invalid level command, {:?}", evt);
+      }
+    }
+  }
+}
+
+fn set_level_state(
+  level_state: &mut LevelState,
+  to: LevelState,
+  transitions: &mut EventWriter<LevelTransition>,
+) {
+  transitions.send(LevelTransition {
+    from: *level_state,
+    to,
+  });
+  *level_state = to;
+}
+
+fn load_level(
+  level_id: u64,
+  sector: Option<&SectorSettings>,
+  levels: &mut Assets<LevelAsset>,
+  asset_server: &AssetServer,
+) -> Handle<LevelAsset> {
+  match sector {
+    Some(sector) if level_id >= sector.first_id => {
+      levels.add(sector::generate_sector(level_id, sector))
+    }
+    _ => asset_server.load(level_path(level_id)),
+  }
+}
+
+fn despawn_level(cmd: &mut Commands, qry_level: &Query<Entity, With<OnLevel>>) {
+  for entity in qry_level.iter() {
+    cmd.entity(entity).despawn_recursive();
+  }
+}
+
+fn unload_level(mut level_cmd: EventWriter<LevelCommand>) {
+  level_cmd.send(LevelCommand::Unload);
+}
+
+fn wait_for_level<T: States>(
+  mut level_state: ResMut<LevelState>,
+  mut transitions: EventWriter<LevelTransition>,
+  mut failed: EventWriter<LevelLoadFailed>,
+  mut next_state: ResMut<NextState<T>>,
+  level_handle: Option<Res<LevelHandle>>,
+  settings: Res<LevelSettings<T>>,
+  levels: Res<Assets<LevelAsset>>,
+  asset_server: Res<AssetServer>,
+) {
+  let (LevelState::Loading(level_id), Some(handle)) = (*level_state, level_handle) else {
+    return;
+  };
+
+  // generated levels never go through the asset server
+  if levels.contains(&handle.0) {
+    info!("level {} loaded", level_id);
+    set_level_state(&mut level_state, LevelState::Loaded(level_id), &mut transitions);
+    next_state.set(settings.level_active_state.clone());
+  } else if asset_server.get_load_state(&handle.0) == LoadState::Failed {
+    error!("failed to load level {}", level_id);
+    set_level_state(&mut level_state, LevelState::Unloaded, &mut transitions);
+    failed.send(LevelLoadFailed(level_id));
+  }
+}
+
+fn wait_for_preload(
+  preloaded: Option<ResMut<PreloadedLevel>>,
+  levels: Res<Assets<LevelAsset>>,
+  asset_server: Res<AssetServer>,
+) {
+  let Some(mut preloaded) = preloaded else {
+    return;
+  };
+  if preloaded.ready || preloaded.failed {
+    return;
+  }
+
+  if levels.contains(&preloaded.handle) {
+    info!("level {} preloaded", preloaded.level_id);
+    preloaded.ready = true;
+  } else if asset_server.get_load_state(&preloaded.handle) == LoadState::Failed {
+    // kept around so the game knows there is no next level
+    warn!("failed to preload level {}", preloaded.level_id);
+    preloaded.failed = true;
+  }
+}
+
+fn spawn_level(
+  cmd: &mut Commands,
+  level: &LevelAsset,
+  chunk_settings: &ChunkSettings,
+  asset_server: &AssetServer,
+) {
+  info!("spawning level {}", level.name);
+
+  cmd.insert_resource(SpawnedLevel(level.clone()));
+  cmd.insert_resource(level.bounds);
+  cmd.insert_resource(LevelObjectives(level.objectives.clone()));
+
+  spawn_skybox(cmd, level, asset_server);
+  spawn_spawn_points(cmd, level);
+  spawn_props(cmd, level, chunk_settings, asset_server);
+  spawn_enemies(cmd, level, asset_server);
+  spawn_pickups(cmd, level);
+}
+
+fn spawn_skybox(cmd: &mut Commands, level: &LevelAsset, asset_server: &AssetServer) {
+  if let Some(skybox) = &level.skybox {
+    cmd.spawn((
+      Cubemap {
+        image: asset_server.load(skybox.as_str()),
+      },
+      LevelPart::Skybox,
+      OnLevel,
+    ));
+  }
+}
+
+fn spawn_spawn_points(cmd: &mut Commands, level: &LevelAsset) {
+  for spawn_point in level.spawn_points.iter() {
+    cmd.spawn((
+      SpatialBundle::from_transform(
+        Transform::from_translation(spawn_point.position)
+          .with_rotation(Quat::from_rotation_y(spawn_point.heading)),
+      ),
+      SpawnPoint,
+      LevelPart::SpawnPoints,
+      OnLevel,
+    ));
+  }
+}
+
+fn spawn_props(
+  cmd: &mut Commands,
+  level: &LevelAsset,
+  chunk_settings: &ChunkSettings,
+  asset_server: &AssetServer,
+) {
+  // untagged props and hazards are spawned as chunks come into range
+  cmd.insert_resource(streaming::LevelChunks::new(level, chunk_settings));
+
+  for prop in level.props.iter() {
+    let Some(tag) = &prop.tag else {
+      continue;
+    };
+    let mut entity = cmd.spawn((
+      prop_bundle(prop, Vec3::ZERO, asset_server),
+      LevelTag(tag.clone()),
+      LevelPart::Props,
+      OnLevel,
+    ));
+    if let Some(radius) = prop.collider_radius {
+      entity.insert((RigidBody::Fixed, Collider::ball(radius)));
+    }
+    if let Some(health) = prop.health {
+      entity.insert(Health::new(health));
+    }
+  }
+}
+
+fn spawn_enemies(cmd: &mut Commands, level: &LevelAsset, asset_server: &AssetServer) {
+  for enemy in level.enemies.iter() {
+    let mut entity = cmd.spawn((
+      SceneBundle {
+        scene: asset_server.load(enemy.model.as_str()),
+        transform: Transform::from_translation(enemy.position)
+          .with_rotation(Quat::from_rotation_y(enemy.heading)),
+        ..default()
+      },
+      LevelEnemy {
+        kind: enemy.kind.clone(),
+      },
+      Health::new(enemy.health),
+      RigidBody::Fixed,
+      Collider::ball(enemy.collider_radius),
+      LevelPart::Enemies,
+      OnLevel,
+    ));
+    if let Some(tag) = &enemy.tag {
+      entity.insert(LevelTag(tag.clone()));
+    }
+    if !enemy.drops.is_empty() {
+      entity.insert(PickupDrops(enemy.drops.clone()));
+    }
+  }
+}
+
+fn spawn_pickups(cmd: &mut Commands, level: &LevelAsset) {
+  for pickup in level.pickups.iter() {
+    cmd.spawn((
+      pickup_bundle(pickup.pickup, pickup.position),
+      LevelPart::Pickups,
+      OnLevel,
+    ));
+  }
+}
+
+/// Respawns the parts of the active level that changed on disk, everything that is not part of the
+/// level (like the player) is left alone
+fn reload_level(
+  mut cmd: Commands,
+  mut events: EventReader<AssetEvent<LevelAsset>>,
+  level_state: Res<LevelState>,
+  level_handle: Option<Res<LevelHandle>>,
+  spawned: Option<Res<SpawnedLevel>>,
+  levels: Res<Assets<LevelAsset>>,
+  qry_parts: Query<(Entity, &LevelPart)>,
+  chunk_settings: Res<ChunkSettings>,
+  asset_server: Res<AssetServer>,
+) {
+  let (LevelState::Active(level_id), Some(handle), Some(spawned)) =
+    (*level_state, level_handle, spawned)
+  else {
+    events.clear();
+    return;
+  };
+
+  let modified = events
+    .iter()
+    .any(|evt| matches!(evt, AssetEvent::Modified { handle: h } if *h == handle.0));
+  let Some(level) = levels.get(&handle.0).filter(|_| modified) else {
+    return;
+  };
+  let old = &spawned.0;
+
+  let mut despawn_part = |part: LevelPart| {
+    for (entity, p) in qry_parts.iter() {
+      if *p == part {
+        cmd.entity(entity).despawn_recursive();
+      }
+    }
+  };
+  let skybox = old.skybox != level.skybox;
+  let spawn_points = old.spawn_points != level.spawn_points;
+  let props = old.props != level.props || old.hazards != level.hazards;
+  let enemies = old.enemies != level.enemies;
+  // collected pickups come back when the pickups change on disk
+  let pickups = old.pickups != level.pickups;
+  if skybox {
+    despawn_part(LevelPart::Skybox);
+  }
+  if spawn_points {
+    despawn_part(LevelPart::SpawnPoints);
+  }
+  if props {
+    despawn_part(LevelPart::Props);
+  }
+  if enemies {
+    despawn_part(LevelPart::Enemies);
+  }
+  if pickups {
+    despawn_part(LevelPart::Pickups);
+  }
+
+  if skybox {
+    spawn_skybox(&mut cmd, level, &asset_server);
+  }
+  if spawn_points {
+    spawn_spawn_points(&mut cmd, level);
+  }
+  if props {
+    spawn_props(&mut cmd, level, &chunk_settings, &asset_server);
+  }
+  if enemies {
+    spawn_enemies(&mut cmd, level, &asset_server);
+  }
+  if pickups {
+    spawn_pickups(&mut cmd, level);
+  }
+  if old.bounds != level.bounds {
+    cmd.insert_resource(level.bounds);
+  }
+  if old.objectives != level.objectives {
+    cmd.insert_resource(LevelObjectives(level.objectives.clone()));
+  }
+
+  info!("reloaded level {}", level_id);
+  cmd.insert_resource(SpawnedLevel(level.clone()));
+}
+
+fn prop_bundle(prop: &PropDef, origin: Vec3, asset_server: &AssetServer) -> SceneBundle {
+  SceneBundle {
+    scene: asset_server.load(prop.model.as_str()),
+    transform: Transform::from_translation(prop.position - origin)
+      .with_rotation(Quat::from_rotation_y(prop.heading))
+      .with_scale(Vec3::splat(prop.scale)),
+    ..default()
+  }
+}
@@ -0,0 +1,236 @@
+use bevy::{math::Vec3Swizzles, prelude::*};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use utils::{
+  level::{
+    DropDef, EnemyDef, HazardDef, LevelAsset, LevelBounds, ObjectiveDef, PropDef, SpawnPointDef,
+  },
+  pickup::PickupKind,
+};
+
+/// When present, `LevelCommand::Load` treats level ids from `first_id` on as seeds and generates
+/// the sector instead of reading a level file
+#[derive(Resource, Clone, Debug)]
+pub struct SectorSettings {
+  /// lower ids are read from level files
+  pub first_id: u64,
+  pub bounds: LevelBounds,
+  /// nothing is generated within this radius of the spawn point
+  pub spawn_clearance: f32,
+  pub asteroid_clusters: u32,
+  pub asteroids_per_cluster: u32,
+  pub stations: u32,
+  pub hazards: u32,
+  pub enemy_camps: u32,
+  pub enemies_per_camp: u32,
+  pub asteroid_model: String,
+  pub station_model: String,
+  pub enemy_model: String,
+  pub skybox: Option<String>,
+}
+
+impl Default for SectorSettings {
+  fn default() -> Self {
+    Self {
+      first_id: 1 << 32,
+      bounds: LevelBounds {
+        min: Vec2::splat(-1000.0),
+        max: Vec2::splat(1000.0),
+      },
+      spawn_clearance: 100.0,
+      asteroid_clusters: 6,
+      asteroids_per_cluster: 8,
+      stations: 2,
+      hazards: 4,
+      enemy_camps: 3,
+      enemies_per_camp: 3,
+      // TODO: replace placeholders once we have proper models
+      asteroid_model: "ship.gltf#Scene0".to_string(),
+      station_model: "ship.gltf#Scene0".to_string(),
+      enemy_model: "ship.gltf#Scene0".to_string(),
+      skybox: Some("skybox/cubemap.png".to_string()),
+    }
+  }
+}
+
+/// Generates the same sector for the same seed and settings
+pub fn generate_sector(seed: u64, settings: &SectorSettings) -> LevelAsset {
+  let mut rng = ChaCha8Rng::seed_from_u64(seed);
+  let spawn = (settings.bounds.min + settings.bounds.max) / 2.0;
+
+  let mut props = Vec::new();
+  let mut enemies = Vec::new();
+  let mut hazards = Vec::new();
+
+  for _ in 0..settings.asteroid_clusters {
+    let center = random_point(&mut rng, settings, spawn);
+    for _ in 0..settings.asteroids_per_cluster {
+      let scale = rng.gen_range(1.0..6.0);
+      props.push(PropDef {
+        model: settings.asteroid_model.clone(),
+        position: scatter(&mut rng, center, 80.0, settings, spawn),
+        heading: rng.gen_range(0.0..std::f32::consts::TAU),
+        scale,
+        collider_radius: Some(5.0),
+        tag: None,
+        health: None,
+      });
+    }
+  }
+
+  for _ in 0..settings.stations {
+    props.push(PropDef {
+      model: settings.station_model.clone(),
+      position: random_point(&mut rng, settings, spawn),
+      heading: rng.gen_range(0.0..std::f32::consts::TAU),
+      scale: 10.0,
+      collider_radius: Some(5.0),
+      tag: None,
+      health: None,
+    });
+  }
+
+  for _ in 0..settings.hazards {
+    hazards.push(HazardDef {
+      kind: "radiation".to_string(),
+      position: random_point(&mut rng, settings, spawn),
+      radius: rng.gen_range(20.0..60.0),
+    });
+  }
+
+  for _ in 0..settings.enemy_camps {
+    let center = random_point(&mut rng, settings, spawn);
+    for _ in 0..settings.enemies_per_camp {
+      enemies.push(EnemyDef {
+        kind: "drone".to_string(),
+        model: settings.enemy_model.clone(),
+        position: scatter(&mut rng, center, 40.0, settings, spawn),
+        heading: rng.gen_range(0.0..std::f32::consts::TAU),
+        tag: None,
+        health: 30.0,
+        collider_radius: 5.0,
+        drops: vec![DropDef {
+          pickup: PickupKind::Health(20.0),
+          chance: 0.25,
+        }],
+      });
+    }
+  }
+
+  LevelAsset {
+    name: format!("Sector {:016x}", seed),
+    skybox: settings.skybox.clone(),
+    bounds: settings.bounds,
+    spawn_points: vec![SpawnPointDef {
+      position: Vec3::new(spawn.x, 0.0, spawn.y),
+      heading: 0.0,
+    }],
+    props,
+    objectives: vec![ObjectiveDef::DestroyTargets {
+      count: enemies.len() as u32,
+      kind: None,
+    }],
+    enemies,
+    hazards,
+    pickups: Vec::new(),
+  }
+}
+
+/// Picks a point inside the bounds, preferably outside the spawn clearance
+fn random_point(rng: &mut ChaCha8Rng, settings: &SectorSettings, spawn: Vec2) -> Vec3 {
+  let bounds = settings.bounds;
+  let mut p = spawn;
+  // give up after a few tries in case the clearance covers most of the sector
+  for _ in 0..32 {
+    p = Vec2::new(
+      rng.gen_range(bounds.min.x..=bounds.max.x),
+      rng.gen_range(bounds.min.y..=bounds.max.y),
+    );
+    if p.distance(spawn) > settings.spawn_clearance {
+      break;
+    }
+  }
+  Vec3::new(p.x, 0.0, p.y)
+}
+
+/// Picks a point around the center, pushed out of the spawn clearance
+fn scatter(
+  rng: &mut ChaCha8Rng,
+  center: Vec3,
+  radius: f32,
+  settings: &SectorSettings,
+  spawn: Vec2,
+) -> Vec3 {
+  let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+  let distance = rng.gen_range(0.0..=radius);
+  let mut p = center.xz() + Vec2::new(angle.cos(), angle.sin()) * distance;
+  if p.distance(spawn) <= settings.spawn_clearance {
+    let away = (p - spawn).try_normalize().unwrap_or(Vec2::X);
+    p = spawn + away * (settings.spawn_clearance + 1.0);
+  }
+  let p = p.clamp(settings.bounds.min, settings.bounds.max);
+  Vec3::new(p.x, 0.0, p.y)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn manifest(seed: u64) -> String {
+    ron::to_string(&generate_sector(seed, &SectorSettings::default())).unwrap()
+  }
+
+  #[test]
+  fn same_seed_same_manifest() {
+    assert_eq!(manifest(42), manifest(42));
+  }
+
+  #[test]
+  fn different_seed_different_manifest() {
+    assert_ne!(manifest(42), manifest(43));
+  }
+
+  #[test]
+  fn everything_inside_bounds() {
+    let settings = SectorSettings::default();
+    let bounds = settings.bounds;
+    let level = generate_sector(7, &settings);
+    let in_bounds = |p: Vec3| p.xz().cmpge(bounds.min).all() && p.xz().cmple(bounds.max).all();
+
+    let positions = level
+      .spawn_points
+      .iter()
+      .map(|s| s.position)
+      .chain(level.props.iter().map(|p| p.position))
+      .chain(level.enemies.iter().map(|e| e.position))
+      .chain(level.hazards.iter().map(|h| h.position))
+      .chain(level.pickups.iter().map(|p| p.position));
+    for p in positions {
+      assert!(in_bounds(p), "{} is outside {:?}", p, bounds);
+    }
+  }
+
+  #[test]
+  fn nothing_inside_spawn_clearance() {
+    let settings = SectorSettings::default();
+    for seed in 0..16 {
+      let level = generate_sector(seed, &settings);
+      let spawn = level.spawn_points[0].position;
+
+      let positions = level
+        .props
+        .iter()
+        .map(|p| p.position)
+        .chain(level.enemies.iter().map(|e| e.position))
+        .chain(level.hazards.iter().map(|h| h.position));
+      for p in positions {
+        assert!(
+          p.distance(spawn) > settings.spawn_clearance,
+          "{} is within {} of the spawn point",
+          p,
+          settings.spawn_clearance
+        );
+      }
+    }
+  }
+}
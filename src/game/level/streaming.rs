@@ -0,0 +1,210 @@
+use bevy::{
+  math::Vec3Swizzles,
+  prelude::*,
+  utils::{HashMap, HashSet},
+};
+use bevy_rapier3d::prelude::*;
+use utils::{
+  level::{HazardDef, LevelAsset, PropDef},
+  vfx::ToonMaterial,
+};
+
+use super::{prop_bundle, LevelHazard, LevelPart, OnLevel};
+use crate::game::camera::PidCameraTarget;
+
+#[derive(Resource, Clone, Debug)]
+pub struct ChunkSettings {
+  pub chunk_size: f32,
+  /// chunks within this many chunks of a camera target are spawned
+  pub load_radius: i32,
+  /// chunks further than this are despawned, keep it above load_radius to avoid thrashing on
+  /// chunk borders
+  pub unload_radius: i32,
+}
+
+impl Default for ChunkSettings {
+  fn default() -> Self {
+    Self {
+      chunk_size: 200.0,
+      load_radius: 2,
+      unload_radius: 3,
+    }
+  }
+}
+
+/// Root of a streamed chunk, its ground, props and hazards are children
+#[derive(Component)]
+pub struct LevelChunk;
+
+#[derive(Default)]
+struct ChunkContents {
+  props: Vec<PropDef>,
+  hazards: Vec<HazardDef>,
+}
+
+/// Static level contents bucketed by chunk, enemies are not streamed since they carry state
+#[derive(Resource, Default)]
+pub(super) struct LevelChunks {
+  contents: HashMap<IVec2, ChunkContents>,
+  loaded: HashMap<IVec2, Entity>,
+}
+
+impl LevelChunks {
+  pub fn new(level: &LevelAsset, settings: &ChunkSettings) -> Self {
+    let mut contents: HashMap<IVec2, ChunkContents> = HashMap::default();
+    // tagged props are referenced by objectives so they are never streamed out
+    for prop in level.props.iter().filter(|p| p.tag.is_none()) {
+      let coord = chunk_coord(prop.position, settings.chunk_size);
+      contents.entry(coord).or_default().props.push(prop.clone());
+    }
+    for hazard in level.hazards.iter() {
+      let coord = chunk_coord(hazard.position, settings.chunk_size);
+      contents.entry(coord).or_default().hazards.push(hazard.clone());
+    }
+
+    Self {
+      contents,
+      loaded: HashMap::default(),
+    }
+  }
+}
+
+#[derive(Resource)]
+pub(super) struct ChunkGround {
+  mesh: Handle<Mesh>,
+  material: Handle<ToonMaterial>,
+}
+
+impl FromWorld for ChunkGround {
+  fn from_world(world: &mut World) -> Self {
+    let chunk_size = world.resource::<ChunkSettings>().chunk_size;
+    let mesh = world
+      .resource_mut::<Assets<Mesh>>()
+      .add(shape::Plane::from_size(chunk_size).into());
+    let material = world.resource_mut::<Assets<ToonMaterial>>().add(ToonMaterial {
+      color: Color::rgb(0.3, 0.5, 0.3).into(),
+      color_texture: None,
+      alpha_mode: AlphaMode::Opaque,
+    });
+    Self { mesh, material }
+  }
+}
+
+fn chunk_coord(pos: Vec3, chunk_size: f32) -> IVec2 {
+  (pos.xz() / chunk_size).floor().as_ivec2()
+}
+
+pub(super) fn stream_chunks(
+  mut cmd: Commands,
+  chunks: Option<ResMut<LevelChunks>>,
+  settings: Res<ChunkSettings>,
+  ground: Res<ChunkGround>,
+  qry_target: Query<&Transform, With<PidCameraTarget>>,
+  asset_server: Res<AssetServer>,
+) {
+  // only present while a level is active
+  let Some(mut chunks) = chunks else {
+    return;
+  };
+
+  let centers: Vec<IVec2> = qry_target
+    .iter()
+    .map(|t| chunk_coord(t.translation, settings.chunk_size))
+    .collect();
+  if centers.is_empty() {
+    return;
+  }
+
+  let within = |coord: IVec2, radius: i32| {
+    centers.iter().any(|c| {
+      let d = (coord - *c).abs();
+      d.x.max(d.y) <= radius
+    })
+  };
+
+  let to_unload: Vec<IVec2> = chunks
+    .loaded
+    .keys()
+    .copied()
+    .filter(|coord| !within(*coord, settings.unload_radius))
+    .collect();
+  for coord in to_unload {
+    if let Some(entity) = chunks.loaded.remove(&coord) {
+      cmd.entity(entity).despawn_recursive();
+    }
+  }
+
+  let mut to_load = HashSet::default();
+  for center in centers.iter() {
+    for x in -settings.load_radius..=settings.load_radius {
+      for y in -settings.load_radius..=settings.load_radius {
+        to_load.insert(*center + IVec2::new(x, y));
+      }
+    }
+  }
+  for coord in to_load {
+    if chunks.loaded.contains_key(&coord) {
+      continue;
+    }
+    let entity = spawn_chunk(
+      &mut cmd,
+      coord,
+      chunks.contents.get(&coord),
+      &settings,
+      &ground,
+      &asset_server,
+    );
+    chunks.loaded.insert(coord, entity);
+  }
+}
+
+fn spawn_chunk(
+  cmd: &mut Commands,
+  coord: IVec2,
+  contents: Option<&ChunkContents>,
+  settings: &ChunkSettings,
+  ground: &ChunkGround,
+  asset_server: &AssetServer,
+) -> Entity {
+  let origin = Vec3::new(coord.x as f32, 0.0, coord.y as f32) * settings.chunk_size;
+
+  cmd
+    .spawn((
+      SpatialBundle::from_transform(Transform::from_translation(origin)),
+      LevelChunk,
+      LevelPart::Props,
+      OnLevel,
+    ))
+    .with_children(|b| {
+      b.spawn(MaterialMeshBundle {
+        mesh: ground.mesh.clone(),
+        material: ground.material.clone(),
+        transform: Transform::from_xyz(settings.chunk_size / 2.0, -10., settings.chunk_size / 2.0),
+        ..default()
+      });
+
+      let Some(contents) = contents else {
+        return;
+      };
+
+      for prop in contents.props.iter() {
+        let mut entity = b.spawn(prop_bundle(prop, origin, asset_server));
+        if let Some(radius) = prop.collider_radius {
+          entity.insert((RigidBody::Fixed, Collider::ball(radius)));
+        }
+      }
+
+      for hazard in contents.hazards.iter() {
+        b.spawn((
+          TransformBundle::from_transform(Transform::from_translation(hazard.position - origin)),
+          Collider::ball(hazard.radius),
+          Sensor,
+          LevelHazard {
+            kind: hazard.kind.clone(),
+            radius: hazard.radius,
+          },
+        ));
+      }
+    })
+    .id()
+}
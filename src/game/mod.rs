@@ -2,18 +2,28 @@ use bevy::{
   core_pipeline::{prepass::{DepthPrepass, NormalPrepass}, bloom::BloomSettings},
   prelude::*,
 };
-use bevy_mod_raycast::RaycastSource;
 use bevy_rapier3d::prelude::*;
-use level::{LevelExtensions, LevelSettings};
+use editor::EditorExtensions;
+use level::{LevelExtensions, LevelHooks, LevelSettings};
 use loading::LoadingExtensions;
-use utils::vfx::{Cubemap, PostProcessSettings, ToonMaterial};
+use utils::{level::GameMode, loadout::SelectedLoadout, vfx::PostProcessSettings};
 
 use self::{camera::PidCamera, player::PlayerExtensions};
 
+mod boost;
 mod camera;
+mod editor;
+mod health;
 mod level;
 mod loading;
+mod modifiers;
+mod objectives;
+mod pickups;
 mod player;
+mod shield;
+mod ship;
+mod turret;
+mod weapons;
 
 #[derive(Resource)]
 struct GameNextState<T>(T);
@@ -22,49 +32,89 @@ pub trait GameExtensions {
 }
 
 impl GameExtensions for App {
-  fn jam<T: States>(&mut self, game_state: T, _exit_state: T) -> &mut Self {
+  fn jam<T: States>(&mut self, game_state: T, exit_state: T) -> &mut Self {
     self
       .add_state::<GameState>()
+      .add_event::<EndGame>()
+      .insert_resource(GameNextState(exit_state))
       .add_loading_screen(GameState::Loading)
       .add_levels(LevelSettings {
         level_active_state: GameState::Playing,
+        sector: Some(default()),
+        chunks: default(),
       })
-      .add_player(player::PlayerSettings)
+      .add_level_editor(GameState::Editor, GameState::Playing, GameState::Loading)
+      .add_player(default())
+      // the editor uses the same input for placing items
+      .configure_set(player::PlayerControlSet.run_if(not(in_state(GameState::Editor))))
       .add_plugin(camera::PidCameraPlugin)
+      .add_plugin(objectives::ObjectivesPlugin)
+      .add_plugin(weapons::WeaponsPlugin)
+      .add_plugin(shield::ShieldPlugin)
+      .add_plugin(boost::BoostPlugin)
+      .add_plugin(turret::TurretPlugin)
+      .add_plugin(health::HealthPlugin)
+      .add_plugin(ship::ShipPlugin)
+      .add_plugin(modifiers::ModifiersPlugin)
+      .add_plugin(pickups::PickupsPlugin)
+      .init_resource::<NextLevel>()
+      .init_resource::<SelectedLoadout>()
+      .init_resource::<GameMode>()
+      .on_level_active(spawn_player)
       .add_systems((
         create_new_game.in_schedule(OnEnter(game_state.clone())),
+        leave_game.in_schedule(OnExit(game_state.clone())),
+        end_game::<T>,
+        handle_load_failed.in_set(OnUpdate(GameState::Loading)),
+        load_level.in_schedule(OnEnter(GameState::Loading)),
+        show_level.in_schedule(OnEnter(GameState::Playing)),
+        handle_out_of_lives.in_set(OnUpdate(GameState::Playing)),
+        handle_replay_restart.in_set(OnUpdate(GameState::Playing)),
+        handle_level_outcome
+          .in_set(OnUpdate(GameState::Playing))
+          .after(handle_out_of_lives),
         // despawn_screen::<OnGameScreen>.in_schedule(OnExit(game_state.clone())),
         // rotate_cam.in_set(OnUpdate(game_state.clone())),
       ))
   }
 }
 
+/// Sent when there is no level left to play, takes the app back to its exit state
+struct EndGame;
+
+/// Level to load the next time the game enters GameState::Loading
+#[derive(Resource, Default)]
+struct NextLevel(u64);
+
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
 enum GameState {
   #[default]
   Disabled,
   Playing,
   Loading,
+  Editor,
 }
 
 fn create_new_game(
   //mut game_time_cmd: EventWriter<GameTimeCommand>,
   mut cmd: Commands,
-  mut level_cmd: EventWriter<level::LevelCommand>,
-  mut player_cmd: EventWriter<player::PlayerCommand>,
+  mut next_level: ResMut<NextLevel>,
   mut game_state: ResMut<NextState<GameState>>,
-  mut meshes: ResMut<Assets<Mesh>>,
-  mut materials: ResMut<Assets<ToonMaterial>>,
-  asset_server: Res<AssetServer>,
+  game_mode: Res<GameMode>,
+  sector: Option<Res<level::SectorSettings>>,
 ) {
   // set sate to loading
   game_state.set(GameState::Loading);
 
   // load the first level
-  level_cmd.send(level::LevelCommand::Load(0));
-
-  // spawn the player
-  player_cmd.send(player::PlayerCommand::Spawn);
+  next_level.0 = match (*game_mode, sector) {
+    (GameMode::Sector, Some(sector)) => sector.first_id + rand::random::<u32>() as u64,
+    (GameMode::Sector, None) => {
+      warn!("sector generation is disabled, starting the campaign");
+      0
+    }
+    (GameMode::Campaign, _) => 0,
+  };
 
   // spawn the camera
   cmd
@@ -86,7 +136,6 @@ fn create_new_game(
       DepthPrepass,
       NormalPrepass,
       PostProcessSettings::default(),
-      RaycastSource::<player::crosshair::CrosshairRaycastSet>::new(),
     ))
     .insert(PidCamera {
       pid: Vec3::new(10.0, 0.0, 0.0),
@@ -105,20 +154,125 @@ fn create_new_game(
       angvel: Vec3::new(0.0, 0.0, 0.0),
     })
     .insert(BloomSettings::default());
+}
+
+fn end_game<T: States>(
+  mut events: EventReader<EndGame>,
+  mut app_state: ResMut<NextState<T>>,
+  exit_state: Res<GameNextState<T>>,
+) {
+  if events.iter().count() > 0 {
+    app_state.set(exit_state.0.clone());
+  }
+}
+
+fn leave_game(
+  mut cmd: Commands,
+  mut game_state: ResMut<NextState<GameState>>,
+  mut player_cmd: EventWriter<player::PlayerCommand>,
+  qry_camera: Query<Entity, With<PidCamera>>,
+) {
+  // leaving GameState::Playing unloads the level
+  game_state.set(GameState::Disabled);
+  player_cmd.send(player::PlayerCommand::Reset);
+  for camera in qry_camera.iter() {
+    cmd.entity(camera).despawn_recursive();
+  }
+}
+
+fn handle_load_failed(
+  mut events: EventReader<level::LevelLoadFailed>,
+  mut end_game: EventWriter<EndGame>,
+) {
+  // a missing level file past the last one means the campaign is over
+  if let Some(level::LevelLoadFailed(level_id)) = events.iter().last() {
+    info!("no level {}, ending the game", level_id);
+    end_game.send(EndGame);
+  }
+}
 
-  // temp so we can see movement
-  cmd.spawn(MaterialMeshBundle {
-    mesh: meshes.add(shape::Plane::from_size(50.0).into()),
-    material: materials.add(ToonMaterial {
-      color: Color::rgb(0.3, 0.5, 0.3).into(),
-      color_texture: None,
-      alpha_mode: AlphaMode::Opaque,
-    }),
-    transform: Transform::from_xyz(0., -10., 0.),
-    ..default()
-  });
-
-  cmd.spawn(Cubemap {
-    image: asset_server.load("skybox/cubemap.png"),
-  });
+fn spawn_player(
+  mut player_cmd: EventWriter<player::PlayerCommand>,
+  qry_player: Query<(), With<player::PlayerComponent>>,
+  loadout: Res<SelectedLoadout>,
+) {
+  // the player carries over when moving between levels
+  if qry_player.is_empty() {
+    player_cmd.send(player::PlayerCommand::Spawn(loadout.0.clone()));
+  }
+}
+
+fn load_level(mut level_cmd: EventWriter<level::LevelCommand>, next_level: Res<NextLevel>) {
+  level_cmd.send(level::LevelCommand::Load(next_level.0));
+}
+
+fn show_level(mut level_cmd: EventWriter<level::LevelCommand>, next_level: Res<NextLevel>) {
+  level_cmd.send(level::LevelCommand::Show);
+  level_cmd.send(level::LevelCommand::Preload(next_level.0 + 1));
+}
+
+fn handle_out_of_lives(
+  mut events: EventReader<player::OutOfLives>,
+  mut outcome: EventWriter<objectives::LevelOutcome>,
+) {
+  // restart the level
+  if events.iter().count() > 0 {
+    outcome.send(objectives::LevelOutcome::Lost);
+  }
+}
+
+/// Replays start from a freshly loaded level with a new ship
+fn handle_replay_restart(
+  mut events: EventReader<player::replay::ReplayRestart>,
+  mut player_cmd: EventWriter<player::PlayerCommand>,
+  mut next_level: ResMut<NextLevel>,
+  mut game_state: ResMut<NextState<GameState>>,
+) {
+  if let Some(evt) = events.iter().last() {
+    next_level.0 = evt.level_id;
+    player_cmd.send(player::PlayerCommand::Reset);
+    game_state.set(GameState::Loading);
+  }
+}
+
+fn handle_level_outcome(
+  mut events: EventReader<objectives::LevelOutcome>,
+  mut level_cmd: EventWriter<level::LevelCommand>,
+  mut next_level: ResMut<NextLevel>,
+  mut game_state: ResMut<NextState<GameState>>,
+  mut end_game: EventWriter<EndGame>,
+  level_state: Res<level::LevelState>,
+  preloaded: Option<Res<level::PreloadedLevel>>,
+) {
+  for evt in events.iter() {
+    let Some(level_id) = level_state.level_id() else {
+      continue;
+    };
+
+    let next_id = match evt {
+      objectives::LevelOutcome::Won => level_id + 1,
+      objectives::LevelOutcome::Lost => level_id,
+    };
+
+    // the preload of the following level failing means this was the last one
+    if preloaded
+      .as_ref()
+      .map_or(false, |p| p.failed && p.level_id == next_id)
+    {
+      info!("level {} was the last one, ending the game", level_id);
+      end_game.send(EndGame);
+      continue;
+    }
+    next_level.0 = next_id;
+
+    match &preloaded {
+      // jump straight into the next level if it is ready
+      Some(p) if p.ready && p.level_id == next_level.0 => {
+        level_cmd.send(level::LevelCommand::Show);
+        level_cmd.send(level::LevelCommand::Preload(next_level.0 + 1));
+      }
+      // leaving GameState::Playing unloads the current level
+      _ => game_state.set(GameState::Loading),
+    }
+  }
 }
@@ -0,0 +1,187 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use utils::pickup::{ModifierDef, Stacking, Stat};
+
+use super::ship::physics_dt;
+
+pub struct ModifiersPlugin;
+impl Plugin for ModifiersPlugin {
+  fn build(&self, app: &mut App) {
+    app.add_system(tick_modifiers);
+  }
+}
+
+/// Temporary stat changes of a ship, systems that use a stat multiply it by [`Modifiers::factor`]
+#[derive(Component, Default)]
+pub struct Modifiers {
+  active: Vec<ActiveModifier>,
+}
+
+struct ActiveModifier {
+  def: ModifierDef,
+  /// seconds left
+  remaining: f32,
+}
+
+impl Modifiers {
+  /// Applies the modifier following its stacking rule
+  pub fn add(&mut self, def: ModifierDef) {
+    let fresh = ActiveModifier {
+      def,
+      remaining: def.seconds,
+    };
+    let mut same_stat = self.active.iter_mut().filter(|m| m.def.stat == def.stat);
+
+    match def.stacking {
+      Stacking::Refresh => match same_stat.next() {
+        Some(m) => *m = fresh,
+        None => self.active.push(fresh),
+      },
+      Stacking::Extend => match same_stat.next() {
+        Some(m) => m.remaining += def.seconds,
+        None => self.active.push(fresh),
+      },
+      Stacking::Stack(max) => {
+        if same_stat.count() < max as usize {
+          self.active.push(fresh);
+        } else if let Some(m) = self
+          .active
+          .iter_mut()
+          .filter(|m| m.def.stat == def.stat)
+          .min_by(|a, b| a.remaining.total_cmp(&b.remaining))
+        {
+          *m = fresh;
+        }
+      }
+    }
+  }
+
+  /// Counts every modifier down and drops the ones that ran out, returns their stats
+  fn tick(&mut self, dt: f32) -> Vec<Stat> {
+    let mut expired = Vec::new();
+    for m in self.active.iter_mut() {
+      m.remaining -= dt;
+    }
+    self.active.retain(|m| {
+      if m.remaining > 0.0 {
+        return true;
+      }
+      expired.push(m.def.stat);
+      false
+    });
+    expired
+  }
+
+  /// Combined multiplier of every active modifier for the stat, 1.0 when there are none
+  pub fn factor(&self, stat: Stat) -> f32 {
+    self
+      .active
+      .iter()
+      .filter(|m| m.def.stat == stat)
+      .map(|m| m.def.factor)
+      .product()
+  }
+}
+
+fn tick_modifiers(
+  mut qry: Query<(Entity, &mut Modifiers)>,
+  config: Res<RapierConfiguration>,
+  time: Res<Time>,
+) {
+  let dt = physics_dt(&config, &time);
+  for (entity, mut modifiers) in qry.iter_mut() {
+    if modifiers.active.is_empty() {
+      continue;
+    }
+    for stat in modifiers.tick(dt) {
+      info!("{:?} modifier of {:?} ran out", stat, entity);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn modifier(stat: Stat, factor: f32, seconds: f32, stacking: Stacking) -> ModifierDef {
+    ModifierDef {
+      stat,
+      factor,
+      seconds,
+      stacking,
+    }
+  }
+
+  fn remaining(modifiers: &Modifiers) -> Vec<f32> {
+    modifiers.active.iter().map(|m| m.remaining).collect()
+  }
+
+  #[test]
+  fn no_modifiers_factor_is_one() {
+    assert_eq!(Modifiers::default().factor(Stat::Speed), 1.0);
+  }
+
+  #[test]
+  fn refresh_replaces() {
+    let mut modifiers = Modifiers::default();
+    modifiers.add(modifier(Stat::Speed, 2.0, 5.0, Stacking::Refresh));
+    modifiers.tick(3.0);
+    modifiers.add(modifier(Stat::Speed, 1.5, 4.0, Stacking::Refresh));
+
+    assert_eq!(remaining(&modifiers), vec![4.0]);
+    assert_eq!(modifiers.factor(Stat::Speed), 1.5);
+  }
+
+  #[test]
+  fn extend_adds_duration() {
+    let mut modifiers = Modifiers::default();
+    modifiers.add(modifier(Stat::Speed, 2.0, 5.0, Stacking::Extend));
+    modifiers.tick(3.0);
+    modifiers.add(modifier(Stat::Speed, 3.0, 4.0, Stacking::Extend));
+
+    assert_eq!(remaining(&modifiers), vec![6.0]);
+    // the first factor is kept
+    assert_eq!(modifiers.factor(Stat::Speed), 2.0);
+  }
+
+  #[test]
+  fn stack_up_to_max() {
+    let mut modifiers = Modifiers::default();
+    modifiers.add(modifier(Stat::FireRate, 2.0, 5.0, Stacking::Stack(2)));
+    modifiers.tick(1.0);
+    modifiers.add(modifier(Stat::FireRate, 1.5, 5.0, Stacking::Stack(2)));
+    assert_eq!(modifiers.factor(Stat::FireRate), 3.0);
+
+    // past the cap the one closest to running out is refreshed
+    modifiers.tick(1.0);
+    modifiers.add(modifier(Stat::FireRate, 1.5, 5.0, Stacking::Stack(2)));
+    assert_eq!(remaining(&modifiers), vec![5.0, 4.0]);
+    assert_eq!(modifiers.factor(Stat::FireRate), 2.25);
+  }
+
+  #[test]
+  fn stats_are_independent() {
+    let mut modifiers = Modifiers::default();
+    modifiers.add(modifier(Stat::Speed, 2.0, 5.0, Stacking::Refresh));
+    modifiers.add(modifier(Stat::FireRate, 1.5, 5.0, Stacking::Refresh));
+    modifiers.add(modifier(Stat::FireRate, 3.0, 5.0, Stacking::Stack(3)));
+
+    assert_eq!(modifiers.factor(Stat::Speed), 2.0);
+    assert_eq!(modifiers.factor(Stat::FireRate), 4.5);
+  }
+
+  #[test]
+  fn expired_modifiers_are_dropped() {
+    let mut modifiers = Modifiers::default();
+    modifiers.add(modifier(Stat::Speed, 2.0, 2.0, Stacking::Stack(2)));
+    modifiers.add(modifier(Stat::Speed, 3.0, 5.0, Stacking::Stack(2)));
+
+    assert!(modifiers.tick(1.0).is_empty());
+    assert_eq!(modifiers.tick(1.0), vec![Stat::Speed]);
+    assert_eq!(modifiers.factor(Stat::Speed), 3.0);
+
+    modifiers.tick(3.0);
+    assert!(modifiers.active.is_empty());
+    assert_eq!(modifiers.factor(Stat::Speed), 1.0);
+  }
+}
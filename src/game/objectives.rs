@@ -0,0 +1,176 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use super::{
+  level::{LevelEnemy, LevelObjectives, LevelTag, ObjectiveDef},
+  player::PlayerComponent,
+  ship::physics_dt,
+};
+
+pub struct ObjectivesPlugin;
+impl Plugin for ObjectivesPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .add_event::<ObjectiveCompleted>()
+      .add_event::<LevelOutcome>()
+      .add_system(track_objectives)
+      .add_system(evaluate_objectives.after(track_objectives));
+  }
+}
+
+/// Index of the completed objective in the level definition
+#[derive(Debug)]
+pub struct ObjectiveCompleted(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelOutcome {
+  Won,
+  Lost,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectiveStatus {
+  InProgress,
+  Completed,
+  Failed,
+}
+
+pub struct ObjectiveProgress {
+  pub def: ObjectiveDef,
+  pub status: ObjectiveStatus,
+  elapsed: f32,
+  /// number of matching entities when the objective started
+  baseline: Option<usize>,
+}
+
+#[derive(Resource)]
+pub struct Objectives {
+  pub progress: Vec<ObjectiveProgress>,
+  pub outcome: Option<LevelOutcome>,
+}
+
+fn track_objectives(mut cmd: Commands, level_objectives: Option<Res<LevelObjectives>>) {
+  match level_objectives {
+    // replaced rather than added when swapping to a preloaded level
+    Some(objectives) if objectives.is_changed() => {
+      cmd.insert_resource(Objectives {
+        progress: objectives
+          .0
+          .iter()
+          .map(|def| ObjectiveProgress {
+            def: def.clone(),
+            status: ObjectiveStatus::InProgress,
+            elapsed: 0.0,
+            baseline: None,
+          })
+          .collect(),
+        outcome: None,
+      });
+    }
+    None => cmd.remove_resource::<Objectives>(),
+    _ => {}
+  }
+}
+
+fn evaluate_objectives(
+  objectives: Option<ResMut<Objectives>>,
+  qry_enemies: Query<&LevelEnemy>,
+  qry_tags: Query<&LevelTag>,
+  qry_player: Query<&Transform, With<PlayerComponent>>,
+  config: Res<RapierConfiguration>,
+  time: Res<Time>,
+  mut completed: EventWriter<ObjectiveCompleted>,
+  mut outcome: EventWriter<LevelOutcome>,
+) {
+  let Some(mut objectives) = objectives else {
+    return;
+  };
+  if objectives.outcome.is_some() {
+    return;
+  }
+  let dt = physics_dt(&config, &time);
+
+  for (index, progress) in objectives.progress.iter_mut().enumerate() {
+    if progress.status != ObjectiveStatus::InProgress {
+      continue;
+    }
+    progress.elapsed += dt;
+
+    progress.status = match &progress.def {
+      ObjectiveDef::DestroyTargets { count, kind } => {
+        let alive = qry_enemies
+          .iter()
+          .filter(|e| kind.as_ref().map_or(true, |k| *k == e.kind))
+          .count();
+        let baseline = *progress.baseline.get_or_insert(alive);
+        if baseline.saturating_sub(alive) >= *count as usize {
+          ObjectiveStatus::Completed
+        } else {
+          ObjectiveStatus::InProgress
+        }
+      }
+      ObjectiveDef::ReachWaypoint { position, radius } => {
+        if qry_player
+          .iter()
+          .any(|t| t.translation.distance(*position) <= *radius)
+        {
+          ObjectiveStatus::Completed
+        } else {
+          ObjectiveStatus::InProgress
+        }
+      }
+      ObjectiveDef::Survive { seconds } => {
+        if progress.elapsed >= *seconds {
+          ObjectiveStatus::Completed
+        } else {
+          ObjectiveStatus::InProgress
+        }
+      }
+      ObjectiveDef::Protect { tag } => {
+        let alive = qry_tags.iter().filter(|t| t.0 == *tag).count();
+        let baseline = *progress.baseline.get_or_insert_with(|| {
+          if alive == 0 {
+            warn!("nothing to protect, no entities tagged {}", tag);
+          }
+          alive
+        });
+        if alive < baseline {
+          ObjectiveStatus::Failed
+        } else {
+          ObjectiveStatus::InProgress
+        }
+      }
+    };
+
+    if progress.status == ObjectiveStatus::Completed {
+      info!("objective {} completed", index);
+      completed.send(ObjectiveCompleted(index));
+    }
+  }
+
+  // protect objectives only ever fail, the level is won once everything else is done
+  let lost = objectives
+    .progress
+    .iter()
+    .any(|p| p.status == ObjectiveStatus::Failed);
+  let mut goals = objectives
+    .progress
+    .iter()
+    .filter(|p| !matches!(p.def, ObjectiveDef::Protect { .. }))
+    .peekable();
+  let won = goals.peek().is_some() && goals.all(|p| p.status == ObjectiveStatus::Completed);
+
+  let result = if lost {
+    Some(LevelOutcome::Lost)
+  } else if won {
+    Some(LevelOutcome::Won)
+  } else {
+    None
+  };
+
+  if let Some(result) = result {
+    info!("level outcome {:?}", result);
+    objectives.outcome = Some(result);
+    outcome.send(result);
+  }
+}
@@ -0,0 +1,221 @@
+use bevy::{prelude::*, utils::HashSet};
+use bevy_rapier3d::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use utils::{level::DropDef, pickup::PickupKind};
+
+use super::{
+  health::{apply_damage, Destroyed, Health},
+  level::OnLevel,
+  modifiers::Modifiers,
+  player::PlayerComponent,
+  shield::Shield,
+  weapons::Weapons,
+};
+
+const PICKUP_RADIUS: f32 = 3.0;
+/// drops of the same enemy are spread around where it was destroyed
+const DROP_SPREAD: f32 = 6.0;
+const SPIN_SPEED: f32 = 1.5;
+
+pub struct PickupsPlugin;
+impl Plugin for PickupsPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .add_event::<PickupCollected>()
+      .init_resource::<PickupAssets>()
+      .insert_resource(PickupRng(ChaCha8Rng::seed_from_u64(0)))
+      .add_system(add_visuals)
+      .add_system(spin_pickups)
+      .add_system(collect_pickups)
+      .add_system(drop_pickups.after(apply_damage));
+  }
+}
+
+#[derive(Component)]
+pub struct Pickup(pub PickupKind);
+
+/// Pickups left behind when the entity is destroyed
+#[derive(Component)]
+pub struct PickupDrops(pub Vec<DropDef>);
+
+#[derive(Debug, Clone, Copy)]
+pub struct PickupCollected {
+  pub ship: Entity,
+  pub kind: PickupKind,
+}
+
+/// Seeded so that replays drop the same pickups
+#[derive(Resource)]
+pub struct PickupRng(ChaCha8Rng);
+
+impl PickupRng {
+  pub fn reseed(&mut self, seed: u64) {
+    self.0 = ChaCha8Rng::seed_from_u64(seed);
+  }
+}
+
+#[derive(Resource)]
+struct PickupAssets {
+  mesh: Handle<Mesh>,
+  health: Handle<StandardMaterial>,
+  shield: Handle<StandardMaterial>,
+  ammo: Handle<StandardMaterial>,
+  modifier: Handle<StandardMaterial>,
+}
+
+impl FromWorld for PickupAssets {
+  fn from_world(world: &mut World) -> Self {
+    let mesh = world
+      .resource_mut::<Assets<Mesh>>()
+      .add(shape::Cube { size: PICKUP_RADIUS }.into());
+    let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+    let mut glow = |r: f32, g: f32, b: f32| {
+      materials.add(StandardMaterial {
+        emissive: Color::rgb(r, g, b),
+        ..default()
+      })
+    };
+    Self {
+      mesh,
+      health: glow(0.5, 6.0, 0.5),
+      shield: glow(0.5, 2.0, 8.0),
+      ammo: glow(6.0, 4.0, 0.5),
+      modifier: glow(6.0, 0.5, 6.0),
+    }
+  }
+}
+
+/// Sensor that is collected by player ships, visuals are added once it is spawned
+pub fn pickup_bundle(
+  kind: PickupKind,
+  position: Vec3,
+) -> (Pickup, SpatialBundle, RigidBody, Collider, Sensor, ActiveEvents) {
+  (
+    Pickup(kind),
+    SpatialBundle::from_transform(Transform::from_translation(position)),
+    RigidBody::Fixed,
+    Collider::ball(PICKUP_RADIUS),
+    Sensor,
+    ActiveEvents::COLLISION_EVENTS,
+  )
+}
+
+fn add_visuals(
+  mut cmd: Commands,
+  qry: Query<(Entity, &Pickup), Added<Pickup>>,
+  assets: Res<PickupAssets>,
+) {
+  for (entity, pickup) in qry.iter() {
+    let material = match pickup.0 {
+      PickupKind::Health(_) => &assets.health,
+      PickupKind::Shield(_) => &assets.shield,
+      PickupKind::Ammo(_) => &assets.ammo,
+      PickupKind::Modifier(_) => &assets.modifier,
+    };
+    cmd
+      .entity(entity)
+      .insert((assets.mesh.clone(), material.clone()));
+  }
+}
+
+fn spin_pickups(mut qry: Query<&mut Transform, With<Pickup>>, time: Res<Time>) {
+  for mut transform in qry.iter_mut() {
+    transform.rotate_y(SPIN_SPEED * time.delta_seconds());
+  }
+}
+
+fn collect_pickups(
+  mut cmd: Commands,
+  mut collisions: EventReader<CollisionEvent>,
+  mut collected: EventWriter<PickupCollected>,
+  qry_pickup: Query<&Pickup>,
+  mut qry_ship: Query<
+    (
+      Option<&mut Health>,
+      Option<&mut Shield>,
+      Option<&mut Weapons>,
+      Option<&mut Modifiers>,
+    ),
+    With<PlayerComponent>,
+  >,
+) {
+  // two ships touching the same pickup in one frame don't both get it
+  let mut taken = HashSet::new();
+
+  for evt in collisions.iter() {
+    let CollisionEvent::Started(a, b, _) = evt else {
+      continue;
+    };
+    for (pickup_entity, ship) in [(*a, *b), (*b, *a)] {
+      let (Ok(pickup), Ok((health, shield, weapons, modifiers))) =
+        (qry_pickup.get(pickup_entity), qry_ship.get_mut(ship))
+      else {
+        continue;
+      };
+      if !taken.insert(pickup_entity) {
+        continue;
+      }
+
+      match pickup.0 {
+        PickupKind::Health(amount) => {
+          if let Some(mut health) = health {
+            health.current = (health.current + amount).min(health.max);
+          }
+        }
+        PickupKind::Shield(amount) => {
+          if let Some(mut shield) = shield {
+            shield.energy = (shield.energy + amount).min(shield.max_energy);
+          }
+        }
+        PickupKind::Ammo(shots) => {
+          if let Some(mut weapons) = weapons {
+            weapons.refill(shots);
+          }
+        }
+        PickupKind::Modifier(def) => {
+          if let Some(mut modifiers) = modifiers {
+            modifiers.add(def);
+          }
+        }
+      }
+
+      info!("{:?} collected {:?}", ship, pickup.0);
+      collected.send(PickupCollected {
+        ship,
+        kind: pickup.0,
+      });
+      cmd.entity(pickup_entity).despawn_recursive();
+    }
+  }
+}
+
+fn drop_pickups(
+  mut cmd: Commands,
+  mut events: EventReader<Destroyed>,
+  qry: Query<(&PickupDrops, &GlobalTransform)>,
+  mut rng: ResMut<PickupRng>,
+) {
+  for evt in events.iter() {
+    let Ok((drops, transform)) = qry.get(evt.entity) else {
+      continue;
+    };
+    let dropped: Vec<PickupKind> = drops
+      .0
+      .iter()
+      .filter(|d| rng.0.gen::<f32>() < d.chance)
+      .map(|d| d.pickup)
+      .collect();
+
+    let origin = transform.translation() * Vec3::new(1.0, 0.0, 1.0);
+    for (i, kind) in dropped.iter().enumerate() {
+      let offset = if dropped.len() > 1 {
+        let angle = i as f32 / dropped.len() as f32 * std::f32::consts::TAU;
+        Quat::from_rotation_y(angle) * Vec3::Z * DROP_SPREAD
+      } else {
+        Vec3::ZERO
+      };
+      cmd.spawn((pickup_bundle(*kind, origin + offset), OnLevel));
+    }
+  }
+}
@@ -1,5 +1,13 @@
 use bevy::{input::mouse::MouseMotion, prelude::*, window::CursorGrabMode};
-use bevy_mod_raycast::{DefaultRaycastingPlugin, RaycastMethod, RaycastSource, RaycastSystem};
+use bevy_mod_raycast::{DefaultRaycastingPlugin, RaycastSource, RaycastSystem};
+
+use super::{
+  input::{Action, ActionInput},
+  PlayerComponent, PlayerId,
+};
+
+/// distance from the ship at which the right stick places the crosshair
+const GAMEPAD_AIM_DISTANCE: f32 = 60.0;
 
 pub struct CrosshairPlugin;
 impl Plugin for CrosshairPlugin {
@@ -11,7 +19,20 @@ impl Plugin for CrosshairPlugin {
           .in_base_set(CoreSet::First)
           .before(RaycastSystem::BuildRays::<CrosshairRaycastSet>),
       )
-      .add_system(read_input)
+      .add_system(
+        read_gamepad
+          .in_base_set(CoreSet::First)
+          .before(RaycastSystem::BuildRays::<CrosshairRaycastSet>),
+      )
+      .add_system(
+        aim_rays
+          .in_base_set(CoreSet::First)
+          .after(update_crosshair_screen_pos)
+          .after(read_gamepad)
+          .before(RaycastSystem::BuildRays::<CrosshairRaycastSet>),
+      )
+      // after the ship input so the click that grabs the cursor doesn't also fire
+      .add_system(read_input.after(super::read_input))
       .add_system(update_crosshair_visibility.after(read_input))
       .add_system(update_crosshair_world_pos);
   }
@@ -20,6 +41,18 @@ impl Plugin for CrosshairPlugin {
 #[derive(Clone, Reflect)]
 pub struct CrosshairRaycastSet;
 
+/// Raycast source of one player's crosshair, the camera can only carry a single screenspace
+/// source so each player casts from its own entity that is moved onto the camera ray
+pub fn crosshair_ray(player: PlayerId) -> impl Bundle {
+  (
+    Name::new("crosshair ray"),
+    player,
+    TransformBundle::default(),
+    RaycastSource::<CrosshairRaycastSet>::new_transform_empty(),
+  )
+}
+
+/// Each player has one, the player on the mouse moves theirs with it and grabs the cursor
 #[derive(Component, Default)]
 pub struct Crosshair {
   pub active: bool,
@@ -27,67 +60,62 @@ pub struct Crosshair {
   pub world_pos: Option<Vec3>,
 }
 
-fn read_input(
-  mut qry_crosshair: Query<&mut Crosshair>,
-  keyboard_input: Res<Input<KeyCode>>,
-  mouse: Res<Input<MouseButton>>,
-) {
-  if let Ok(mut c) = qry_crosshair.get_single_mut() {
-    if mouse.just_pressed(MouseButton::Left) {
+fn read_input(mut qry_crosshair: Query<(&PlayerId, &mut Crosshair)>, input: ActionInput) {
+  for (player, mut c) in qry_crosshair.iter_mut() {
+    let input = input.player(*player);
+    if !input.uses_mouse() {
+      continue;
+    }
+
+    if !c.active && input.just_pressed(Action::GrabCursor) {
       c.active = true;
     }
 
-    if keyboard_input.just_pressed(KeyCode::Escape) {
+    if input.just_pressed(Action::ReleaseCursor) {
       c.active = false;
     }
   }
 }
 
 fn update_crosshair_visibility(
-  mut qry_crosshair: Query<(&mut Crosshair, &mut Style, &mut Visibility), Changed<Crosshair>>,
+  mut qry_crosshair: Query<
+    (&PlayerId, &mut Crosshair, &mut Style, &mut Visibility),
+    Changed<Crosshair>,
+  >,
   mut windows: Query<&mut Window>,
+  input: ActionInput,
 ) {
   let mut window = windows.single_mut();
   // refactor to use cmds if crosshair interaction gets complicated
 
-  if let Ok((mut c, mut style, mut v)) = qry_crosshair.get_single_mut() {
+  for (player, mut c, mut style, mut v) in qry_crosshair.iter_mut() {
+    let uses_mouse = input.player(*player).uses_mouse();
     if c.active {
-      window.cursor.visible = false;
-      window.cursor.grab_mode = CursorGrabMode::Locked;
+      if uses_mouse {
+        window.cursor.visible = false;
+        window.cursor.grab_mode = CursorGrabMode::Locked;
+      }
       *v = Visibility::Visible;
 
-      if c.last_pos == None {
-        let cursor_pos = if let Some(cp) = window.cursor_position() {
-          cp
-        } else {
-          Vec2::new(
-            window.resolution.width() / 2.0,
-            window.resolution.height() / 2.0,
-          )
-        };
-
-        let w = if let Val::Px(r) = style.size.width {
-          r / 2.0
-        } else {
-          0.0
-        };
-        let h = if let Val::Px(r) = style.size.height {
-          r / 2.0
-        } else {
-          0.0
-        };
+      if c.last_pos.is_none() {
+        let cursor_pos = window
+          .cursor_position()
+          .filter(|_| uses_mouse)
+          .unwrap_or_else(|| {
+            Vec2::new(
+              window.resolution.width() / 2.0,
+              window.resolution.height() / 2.0,
+            )
+          });
 
         c.last_pos = Some(cursor_pos);
-        style.position = UiRect::new(
-          Val::Px(cursor_pos.x - w),
-          Val::Undefined,
-          Val::Undefined,
-          Val::Px(cursor_pos.y - h),
-        );
+        set_screen_pos(&mut style, cursor_pos);
       }
     } else {
-      window.cursor.visible = true;
-      window.cursor.grab_mode = CursorGrabMode::None;
+      if uses_mouse {
+        window.cursor.visible = true;
+        window.cursor.grab_mode = CursorGrabMode::None;
+      }
       *v = Visibility::Hidden;
     }
   }
@@ -95,57 +123,125 @@ fn update_crosshair_visibility(
 
 fn update_crosshair_screen_pos(
   mut mouse_motion_events: EventReader<MouseMotion>,
-  mut qry_crosshair: Query<(&mut Crosshair, &mut Style)>,
-  mut qry_raycast: Query<&mut RaycastSource<CrosshairRaycastSet>>,
+  mut qry_crosshair: Query<(&PlayerId, &mut Crosshair, &mut Style)>,
   windows: Query<&Window>,
+  input: ActionInput,
 ) {
   let window = windows.single();
+  let delta: Vec2 = mouse_motion_events.iter().map(|e| e.delta).sum();
+  if delta == Vec2::ZERO {
+    return;
+  }
 
-  for event in mouse_motion_events.iter() {
-    if let Ok((mut c, mut style)) = qry_crosshair.get_single_mut() {
-      if !c.active {
-        break;
-      }
+  for (player, mut c, mut style) in qry_crosshair.iter_mut() {
+    if !c.active || !input.player(*player).uses_mouse() {
+      continue;
+    }
 
-      // TODO: send player commend to orient ship
-      match (c.last_pos, style.size.width, style.size.height) {
-        (Some(last_pos), Val::Px(w), Val::Px(h)) => {
-          let new_pos = Vec2::new(last_pos.x + event.delta.x, last_pos.y - event.delta.y).clamp(
-            Vec2::new(0., 0.),
-            Vec2::new(window.resolution.width(), window.resolution.height()),
-          );
-          c.last_pos = Some(new_pos);
-          style.position = UiRect::new(
-            Val::Px(new_pos.x - (w / 2.0)),
-            Val::Undefined,
-            Val::Undefined,
-            Val::Px(new_pos.y - (h / 2.0)),
-          );
-
-          for mut pick_source in &mut qry_raycast {
-            pick_source.cast_method = RaycastMethod::Screenspace(new_pos);
-          }
-        }
-        _ => {
-          warn!("cannot update crosshair pos");
-        }
-      }
+    // TODO: send player commend to orient ship
+    let Some(last_pos) = c.last_pos else {
+      warn!("cannot update crosshair pos");
+      continue;
+    };
+    let new_pos = Vec2::new(last_pos.x + delta.x, last_pos.y - delta.y).clamp(
+      Vec2::new(0., 0.),
+      Vec2::new(window.resolution.width(), window.resolution.height()),
+    );
+    c.last_pos = Some(new_pos);
+    set_screen_pos(&mut style, new_pos);
+  }
+}
+
+/// Aim stick aims around the player's ship, the crosshair moves to where the stick points
+fn read_gamepad(
+  input: ActionInput,
+  mut qry_crosshair: Query<(&PlayerId, &mut Crosshair, &mut Style)>,
+  qry_camera: Query<(&Camera, &GlobalTransform)>,
+  qry_player: Query<(&PlayerId, &Transform), With<PlayerComponent>>,
+) {
+  let Ok((camera, camera_transform)) = qry_camera.get_single() else {
+    return;
+  };
+
+  for (player, mut c, mut style) in qry_crosshair.iter_mut() {
+    let stick = input.player(*player).aim_stick();
+    if stick == Vec2::ZERO {
+      continue;
     }
+    let Some((_, ship)) = qry_player.iter().find(|(id, _)| *id == player) else {
+      continue;
+    };
+
+    let target =
+      ship.translation + super::stick_to_world(stick).normalize() * GAMEPAD_AIM_DISTANCE;
+    let Some(screen_pos) = camera.world_to_viewport(camera_transform, target) else {
+      continue;
+    };
+    set_screen_pos(&mut style, screen_pos);
+    c.active = true;
+    c.last_pos = Some(screen_pos);
   }
 }
 
-fn update_crosshair_world_pos(
-  mut crosshair: Query<&mut Crosshair>,
-  to: Query<&RaycastSource<CrosshairRaycastSet>>,
+/// Points each player's raycast source from the camera through their crosshair
+fn aim_rays(
+  qry_crosshair: Query<(&PlayerId, &Crosshair)>,
+  mut qry_ray: Query<
+    (&PlayerId, &mut Transform, &mut GlobalTransform),
+    (With<RaycastSource<CrosshairRaycastSet>>, Without<Camera>),
+  >,
+  qry_camera: Query<(&Camera, &GlobalTransform)>,
 ) {
-  if let Ok(raycast_source) = to.get_single() {
-    if let Some(top_intersection) = raycast_source.get_nearest_intersection() {
-      let mut new_pos = top_intersection.1.position();
-      new_pos.y = 0.0;
+  let Ok((camera, camera_transform)) = qry_camera.get_single() else {
+    return;
+  };
+
+  for (player, mut transform, mut global_transform) in qry_ray.iter_mut() {
+    let Some(ray) = qry_crosshair
+      .iter()
+      .find(|(id, _)| *id == player)
+      .and_then(|(_, c)| c.last_pos)
+      .and_then(|p| camera.viewport_to_world(camera_transform, p))
+    else {
+      continue;
+    };
+    // transform rays are cast along -Z
+    *transform = Transform::from_translation(ray.origin)
+      .with_rotation(Quat::from_rotation_arc(Vec3::NEG_Z, ray.direction));
+    // the rays are built before transforms are propagated
+    *global_transform = GlobalTransform::from(*transform);
+  }
+}
 
-      if let Ok(mut c) = crosshair.get_single_mut() {
-        c.world_pos = Some(new_pos)
-      }
+fn update_crosshair_world_pos(
+  mut qry_crosshair: Query<(&PlayerId, &mut Crosshair)>,
+  qry_ray: Query<(&PlayerId, &RaycastSource<CrosshairRaycastSet>)>,
+) {
+  for (player, raycast_source) in qry_ray.iter() {
+    let Some((_, intersection)) = raycast_source.get_nearest_intersection() else {
+      continue;
+    };
+    let Some((_, mut c)) = qry_crosshair.iter_mut().find(|(id, _)| *id == player) else {
+      continue;
+    };
+    let mut new_pos = intersection.position();
+    new_pos.y = 0.0;
+
+    if c.world_pos != Some(new_pos) {
+      c.world_pos = Some(new_pos);
     }
   }
 }
+
+/// Centers the crosshair image on a position measured from the bottom left of the window
+fn set_screen_pos(style: &mut Style, pos: Vec2) {
+  let (Val::Px(w), Val::Px(h)) = (style.size.width, style.size.height) else {
+    return;
+  };
+  style.position = UiRect::new(
+    Val::Px(pos.x - (w / 2.0)),
+    Val::Undefined,
+    Val::Undefined,
+    Val::Px(pos.y - (h / 2.0)),
+  );
+}
@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+use utils::{level::ObjectiveDef, pickup::PickupKind};
+
+use super::{player_color, PlayerComponent, PlayerId, PlayerState};
+use crate::game::{
+  boost::Boost,
+  health::Health,
+  objectives::{ObjectiveCompleted, Objectives},
+  pickups::PickupCollected,
+  shield::Shield,
+};
+
+/// seconds a collected pickup stays on the status line
+const PICKUP_NOTICE: f32 = 2.0;
+/// seconds a completed objective stays on the status line
+const OBJECTIVE_NOTICE: f32 = 3.0;
+
+/// Status line of one player, each player gets a corner of the screen
+#[derive(Component, Default)]
+pub(super) struct Hud {
+  /// last collected pickup and the seconds it is still shown for
+  pickup: Option<(PickupKind, f32)>,
+  /// last completed objective and the seconds it is still shown for
+  objective: Option<(String, f32)>,
+}
+
+pub(super) fn spawn_hud(
+  cmd: &mut Commands,
+  asset_server: &AssetServer,
+  player: PlayerId,
+) -> Entity {
+  let margin = Val::Px(10.0);
+  let (left, right) = if player.0 % 2 == 0 {
+    (margin, Val::Undefined)
+  } else {
+    (Val::Undefined, margin)
+  };
+  let (top, bottom) = if player.0 < 2 {
+    (margin, Val::Undefined)
+  } else {
+    (Val::Undefined, margin)
+  };
+
+  cmd
+    .spawn((
+      TextBundle::from_section(
+        "",
+        TextStyle {
+          font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+          font_size: 24.0,
+          color: player_color(player),
+        },
+      )
+      .with_style(Style {
+        position_type: PositionType::Absolute,
+        position: UiRect::new(left, right, top, bottom),
+        ..default()
+      }),
+      Hud::default(),
+      player,
+    ))
+    .id()
+}
+
+pub(super) fn show_pickups(
+  mut events: EventReader<PickupCollected>,
+  qry_ship: Query<&PlayerId, With<PlayerComponent>>,
+  mut qry_hud: Query<(&PlayerId, &mut Hud)>,
+  time: Res<Time>,
+) {
+  for (_, mut hud) in qry_hud.iter_mut() {
+    if let Some((_, remaining)) = hud.pickup.as_mut() {
+      *remaining -= time.delta_seconds();
+    }
+    if hud.pickup.map_or(false, |(_, remaining)| remaining <= 0.0) {
+      hud.pickup = None;
+    }
+  }
+
+  for evt in events.iter() {
+    let Ok(player) = qry_ship.get(evt.ship) else {
+      continue;
+    };
+    if let Some((_, mut hud)) = qry_hud.iter_mut().find(|(id, _)| *id == player) {
+      hud.pickup = Some((evt.kind, PICKUP_NOTICE));
+    }
+  }
+}
+
+pub(super) fn show_objectives(
+  mut events: EventReader<ObjectiveCompleted>,
+  objectives: Option<Res<Objectives>>,
+  mut qry_hud: Query<&mut Hud>,
+  time: Res<Time>,
+) {
+  for mut hud in qry_hud.iter_mut() {
+    if let Some((_, remaining)) = hud.objective.as_mut() {
+      *remaining -= time.delta_seconds();
+    }
+    if hud.objective.as_ref().map_or(false, |(_, remaining)| *remaining <= 0.0) {
+      hud.objective = None;
+    }
+  }
+
+  for ObjectiveCompleted(index) in events.iter() {
+    let Some(progress) = objectives.as_ref().and_then(|o| o.progress.get(*index)) else {
+      continue;
+    };
+    // objectives belong to the level so every player sees them
+    let label = objective_label(&progress.def);
+    for mut hud in qry_hud.iter_mut() {
+      hud.objective = Some((label.clone(), OBJECTIVE_NOTICE));
+    }
+  }
+}
+
+/// Notices belong to the level they were shown on
+pub(super) fn clear_notices(mut qry_hud: Query<&mut Hud>) {
+  for mut hud in qry_hud.iter_mut() {
+    hud.pickup = None;
+    hud.objective = None;
+  }
+}
+
+pub(super) fn update_hud(
+  player_state: Res<PlayerState>,
+  qry_ship: Query<(&PlayerId, &Health, &Shield, &Boost), With<PlayerComponent>>,
+  mut qry_hud: Query<(&PlayerId, &Hud, &mut Text)>,
+) {
+  for (player, health, shield, boost) in qry_ship.iter() {
+    let Some((_, hud, mut text)) = qry_hud.iter_mut().find(|(id, _, _)| *id == player) else {
+      continue;
+    };
+    let (lives, kills) = player_state
+      .slots
+      .get(player.0)
+      .map_or((0, 0), |s| (s.lives, s.kills));
+    let mut status = format!(
+      "P{}  lives {}  kills {}  hull {:.0}  shield {:.0}{}  boost {:.0}",
+      player.0 + 1,
+      lives,
+      kills,
+      health.current,
+      shield.energy,
+      if shield.active() { " up" } else { "" },
+      boost.energy
+    );
+    if let Some((kind, _)) = hud.pickup {
+      status.push_str("  ");
+      status.push_str(&pickup_label(kind));
+    }
+    if let Some((label, _)) = &hud.objective {
+      status.push_str("  ");
+      status.push_str(label);
+    }
+    text.sections[0].value = status;
+  }
+}
+
+fn pickup_label(kind: PickupKind) -> String {
+  match kind {
+    PickupKind::Health(amount) => format!("+{:.0} hull", amount),
+    PickupKind::Shield(amount) => format!("+{:.0} shield", amount),
+    PickupKind::Ammo(shots) => format!("+{} ammo", shots),
+    PickupKind::Modifier(def) => {
+      format!("{:?} x{:.1} for {:.0}s", def.stat, def.factor, def.seconds)
+    }
+  }
+}
+
+fn objective_label(def: &ObjectiveDef) -> String {
+  match def {
+    ObjectiveDef::DestroyTargets { count, .. } => format!("{} targets destroyed", count),
+    ObjectiveDef::ReachWaypoint { .. } => "waypoint reached".to_string(),
+    ObjectiveDef::Survive { seconds } => format!("survived {:.0}s", seconds),
+    ObjectiveDef::Protect { tag } => format!("{} protected", tag),
+  }
+}
@@ -0,0 +1,363 @@
+use std::{collections::BTreeMap, path::Path};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use super::PlayerId;
+
+/// user config, relative to the working directory so it stays writable outside of assets
+const INPUT_MAP_PATH: &str = "config/input.ron";
+/// a dead zone covering the whole stick would leave nothing to rescale
+const MAX_DEAD_ZONE: f32 = 0.99;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Action {
+  MoveForward,
+  MoveLeft,
+  MoveBack,
+  MoveRight,
+  Fire,
+  Shield,
+  /// short burst of extra thrust
+  Boost,
+  CycleWeapon,
+  /// shows the crosshair and grabs the cursor, only while the cursor is released so it can share
+  /// a button with firing
+  GrabCursor,
+  ReleaseCursor,
+  /// restarts the level and records until pressed again
+  ToggleRecording,
+  /// restarts the level and plays the last recording
+  PlayReplay,
+  /// switches between playing and the level editor
+  ToggleEditor,
+  EditorSave,
+  /// places the current palette item, or drops the grabbed one
+  EditorPlace,
+  /// selects the item under the cursor
+  EditorSelect,
+  /// moves the selected item with the cursor until placed
+  EditorGrab,
+  EditorDelete,
+  EditorRotateLeft,
+  EditorRotateRight,
+  /// picks a palette item by its index
+  EditorPalette(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Binding {
+  Key(KeyCode),
+  Mouse(MouseButton),
+  Gamepad(GamepadButtonType),
+}
+
+/// Inputs that drive one local player
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Device {
+  /// keyboard, mouse and the first gamepad, for playing alone
+  Any,
+  KeyboardMouse,
+  /// gamepad by id, ids are handed out in the order gamepads are connected
+  Gamepad(usize),
+}
+
+impl Device {
+  pub fn uses_mouse(&self) -> bool {
+    matches!(self, Device::Any | Device::KeyboardMouse)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Stick {
+  Left,
+  Right,
+}
+
+impl Stick {
+  pub fn axes(&self) -> (GamepadAxisType, GamepadAxisType) {
+    match self {
+      Stick::Left => (GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY),
+      Stick::Right => (GamepadAxisType::RightStickX, GamepadAxisType::RightStickY),
+    }
+  }
+}
+
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputMap {
+  pub bindings: BTreeMap<Action, Vec<Binding>>,
+  pub move_stick: Stick,
+  pub aim_stick: Stick,
+  /// stick deflection below this is ignored
+  pub stick_dead_zone: f32,
+  /// one device per local player
+  pub players: Vec<Device>,
+}
+
+impl Default for InputMap {
+  fn default() -> Self {
+    use Binding::*;
+
+    Self {
+      bindings: BTreeMap::from([
+        (Action::MoveForward, vec![Key(KeyCode::W)]),
+        (Action::MoveLeft, vec![Key(KeyCode::A)]),
+        (Action::MoveBack, vec![Key(KeyCode::S)]),
+        (Action::MoveRight, vec![Key(KeyCode::D)]),
+        (
+          Action::Fire,
+          vec![Mouse(MouseButton::Left), Gamepad(GamepadButtonType::RightTrigger2)],
+        ),
+        (
+          Action::Shield,
+          vec![Mouse(MouseButton::Right), Gamepad(GamepadButtonType::LeftTrigger2)],
+        ),
+        (
+          Action::Boost,
+          vec![Key(KeyCode::Space), Gamepad(GamepadButtonType::South)],
+        ),
+        (
+          Action::CycleWeapon,
+          vec![
+            Key(KeyCode::Tab),
+            Gamepad(GamepadButtonType::RightTrigger),
+            Gamepad(GamepadButtonType::LeftTrigger),
+          ],
+        ),
+        (Action::GrabCursor, vec![Mouse(MouseButton::Left)]),
+        (Action::ReleaseCursor, vec![Key(KeyCode::Escape)]),
+        (Action::ToggleRecording, vec![Key(KeyCode::F9)]),
+        (Action::PlayReplay, vec![Key(KeyCode::F10)]),
+        (Action::ToggleEditor, vec![Key(KeyCode::F2)]),
+        (Action::EditorSave, vec![Key(KeyCode::F5)]),
+        (Action::EditorPlace, vec![Mouse(MouseButton::Left)]),
+        (Action::EditorSelect, vec![Mouse(MouseButton::Right)]),
+        (Action::EditorGrab, vec![Key(KeyCode::G)]),
+        (Action::EditorDelete, vec![Key(KeyCode::Delete)]),
+        (Action::EditorRotateLeft, vec![Key(KeyCode::Q)]),
+        (Action::EditorRotateRight, vec![Key(KeyCode::E)]),
+        (Action::EditorPalette(0), vec![Key(KeyCode::Key1)]),
+        (Action::EditorPalette(1), vec![Key(KeyCode::Key2)]),
+        (Action::EditorPalette(2), vec![Key(KeyCode::Key3)]),
+        (Action::EditorPalette(3), vec![Key(KeyCode::Key4)]),
+        (Action::EditorPalette(4), vec![Key(KeyCode::Key5)]),
+        (Action::EditorPalette(5), vec![Key(KeyCode::Key6)]),
+        (Action::EditorPalette(6), vec![Key(KeyCode::Key7)]),
+        (Action::EditorPalette(7), vec![Key(KeyCode::Key8)]),
+        (Action::EditorPalette(8), vec![Key(KeyCode::Key9)]),
+      ]),
+      move_stick: Stick::Left,
+      aim_stick: Stick::Right,
+      stick_dead_zone: 0.2,
+      players: vec![Device::Any],
+    }
+  }
+}
+
+impl InputMap {
+  /// Reads the user config, actions missing from it keep their default bindings
+  pub fn load() -> Self {
+    let path = Path::new(INPUT_MAP_PATH);
+    if !path.is_file() {
+      let map = Self::default();
+      map.save();
+      return map;
+    }
+
+    let loaded = std::fs::read(path)
+      .map_err(|e| e.to_string())
+      .and_then(|bytes| ron::de::from_bytes::<InputMap>(&bytes).map_err(|e| e.to_string()));
+    match loaded {
+      Ok(mut map) => {
+        for (action, bindings) in Self::default().bindings {
+          map.bindings.entry(action).or_insert(bindings);
+        }
+        if map.players.is_empty() {
+          map.players.push(Device::Any);
+        }
+        if !(0.0..=MAX_DEAD_ZONE).contains(&map.stick_dead_zone) {
+          warn!("stick_dead_zone {} is not within 0..{}", map.stick_dead_zone, MAX_DEAD_ZONE);
+          map.stick_dead_zone = if map.stick_dead_zone.is_nan() {
+            Self::default().stick_dead_zone
+          } else {
+            map.stick_dead_zone.clamp(0.0, MAX_DEAD_ZONE)
+          };
+        }
+        map
+      }
+      Err(e) => {
+        warn!("cannot read {}, using default bindings: {}", INPUT_MAP_PATH, e);
+        Self::default()
+      }
+    }
+  }
+
+  /// Name of the first binding of an action, for hints in the UI
+  pub fn binding_name(&self, action: Action) -> String {
+    match self.bindings.get(&action).and_then(|b| b.first()) {
+      Some(Binding::Key(key)) => format!("{:?}", key),
+      Some(Binding::Mouse(button)) => format!("{:?} mouse", button),
+      Some(Binding::Gamepad(button)) => format!("{:?}", button),
+      None => "unbound".to_string(),
+    }
+  }
+
+  pub fn save(&self) {
+    let saved = ron::ser::to_string_pretty(self, default())
+      .map_err(|e| e.to_string())
+      .and_then(|data| {
+        let path = Path::new(INPUT_MAP_PATH);
+        if let Some(dir) = path.parent() {
+          std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(path, data).map_err(|e| e.to_string())
+      });
+    match saved {
+      Ok(()) => info!("saved input map to {}", INPUT_MAP_PATH),
+      Err(e) => warn!("cannot save input map to {}: {}", INPUT_MAP_PATH, e),
+    }
+  }
+}
+
+/// Rebinding at runtime only needs to change the resource
+pub(super) fn save_input_map(input_map: Res<InputMap>) {
+  if input_map.is_changed() && !input_map.is_added() {
+    input_map.save();
+  }
+}
+
+/// Checks actions against every bound key, mouse button and gamepad, either for all devices at
+/// once or for a single player
+#[derive(SystemParam)]
+pub struct ActionInput<'w> {
+  input_map: Res<'w, InputMap>,
+  keys: Res<'w, Input<KeyCode>>,
+  mouse: Res<'w, Input<MouseButton>>,
+  gamepads: Res<'w, Gamepads>,
+  gamepad_buttons: Res<'w, Input<GamepadButton>>,
+  axes: Res<'w, Axis<GamepadAxis>>,
+}
+
+impl<'w> ActionInput<'w> {
+  /// Keyboard, mouse and the first gamepad, for actions that don't belong to a player
+  pub fn just_pressed(&self, action: Action) -> bool {
+    self.any_device().just_pressed(action)
+  }
+
+  pub fn pressed(&self, action: Action) -> bool {
+    self.any_device().pressed(action)
+  }
+
+  fn any_device(&self) -> PlayerInput<'_, 'w> {
+    PlayerInput {
+      input: self,
+      device: Some(Device::Any),
+      gamepad: self.gamepad(Device::Any),
+    }
+  }
+
+  pub fn players(&self) -> impl Iterator<Item = PlayerId> {
+    (0..self.input_map.players.len()).map(PlayerId)
+  }
+
+  /// Input of a single player, players without a device get no input
+  pub fn player(&self, player: PlayerId) -> PlayerInput<'_, 'w> {
+    let device = self.input_map.players.get(player.0).copied();
+    PlayerInput {
+      input: self,
+      device,
+      gamepad: device.and_then(|d| self.gamepad(d)),
+    }
+  }
+
+  fn gamepad(&self, device: Device) -> Option<Gamepad> {
+    match device {
+      Device::Any => self.gamepads.iter().next(),
+      Device::KeyboardMouse => None,
+      Device::Gamepad(id) => self.gamepads.iter().find(|g| g.id == id),
+    }
+  }
+}
+
+pub struct PlayerInput<'a, 'w> {
+  input: &'a ActionInput<'w>,
+  device: Option<Device>,
+  gamepad: Option<Gamepad>,
+}
+
+impl<'a, 'w> PlayerInput<'a, 'w> {
+  pub fn uses_mouse(&self) -> bool {
+    self.device.map_or(false, |d| d.uses_mouse())
+  }
+
+  pub fn pressed(&self, action: Action) -> bool {
+    self.any_binding(action, |binding| self.binding_pressed(binding))
+  }
+
+  /// Ignores mouse buttons, for when a click is meant for something else like grabbing the cursor
+  pub fn pressed_without_mouse(&self, action: Action) -> bool {
+    self.any_binding(action, |binding| {
+      !matches!(binding, Binding::Mouse(_)) && self.binding_pressed(binding)
+    })
+  }
+
+  pub fn just_pressed(&self, action: Action) -> bool {
+    self.any_binding(action, |binding| match binding {
+      Binding::Key(key) => self.input.keys.just_pressed(key),
+      Binding::Mouse(button) => self.input.mouse.just_pressed(button),
+      Binding::Gamepad(button) => self.gamepad.map_or(false, |g| {
+        self.input.gamepad_buttons.just_pressed(GamepadButton::new(g, button))
+      }),
+    })
+  }
+
+  pub fn move_stick(&self) -> Vec2 {
+    self.stick(self.input.input_map.move_stick)
+  }
+
+  pub fn aim_stick(&self) -> Vec2 {
+    self.stick(self.input.input_map.aim_stick)
+  }
+
+  fn binding_pressed(&self, binding: Binding) -> bool {
+    match binding {
+      Binding::Key(key) => self.input.keys.pressed(key),
+      Binding::Mouse(button) => self.input.mouse.pressed(button),
+      Binding::Gamepad(button) => self.gamepad.map_or(false, |g| {
+        self.input.gamepad_buttons.pressed(GamepadButton::new(g, button))
+      }),
+    }
+  }
+
+  /// Keys and mouse buttons only count for players on the keyboard
+  fn any_binding(&self, action: Action, f: impl Fn(Binding) -> bool) -> bool {
+    let uses_mouse = self.uses_mouse();
+    self.input.input_map.bindings.get(&action).map_or(false, |bindings| {
+      bindings
+        .iter()
+        .copied()
+        .filter(|b| uses_mouse || matches!(b, Binding::Gamepad(_)))
+        .any(f)
+    })
+  }
+
+  /// Stick position with a radial dead zone, rescaled so it still reaches 1.0 at full deflection
+  fn stick(&self, stick: Stick) -> Vec2 {
+    let Some(gamepad) = self.gamepad else {
+      return Vec2::ZERO;
+    };
+    let axes = &self.input.axes;
+    let (x, y) = stick.axes();
+    let value = Vec2::new(
+      axes.get(GamepadAxis::new(gamepad, x)).unwrap_or_default(),
+      axes.get(GamepadAxis::new(gamepad, y)).unwrap_or_default(),
+    );
+    let dead_zone = self.input.input_map.stick_dead_zone;
+    let length = value.length();
+    if length <= dead_zone || length == 0.0 {
+      return Vec2::ZERO;
+    }
+    value / length * ((length.min(1.0) - dead_zone) / (1.0 - dead_zone))
+  }
+}
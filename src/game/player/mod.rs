@@ -1,16 +1,34 @@
+use std::time::Duration;
+
 use bevy::ecs::system::WithEntity;
-use bevy::{math::Vec3Swizzles, prelude::*};
+use bevy::prelude::*;
 use bevy_hanabi::prelude::*;
 use bevy_hanabi::EffectAsset;
 use bevy_mod_raycast::RaycastMesh;
 use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use super::camera::PidCameraTarget; // TODO: make player extensible
+use super::{
+  boost::{update_boosts, Boost, BoostCommand},
+  camera::PidCameraTarget, // TODO: make player extensible
+  health::{apply_damage, Destroyed, Health, ImpactDamage, Invulnerable},
+  level::{LevelEnemy, LevelHooks, SpawnPoint},
+  modifiers::Modifiers,
+  ship::{fly_ships, physics_dt, ShipInput},
+  shield::{Shield, ShieldCommand},
+  turret::{Turret, TurretCommand},
+  weapons::{WeaponCommand, Weapons},
+};
+use self::input::{Action, ActionInput};
+use utils::{loadout::ShipLoadout, ron_asset::RonAssetExtensions};
 
 pub mod crosshair;
+mod hud;
+pub mod input;
+pub mod replay;
+mod settings;
 
-#[derive(Default, Clone, Resource)]
-pub struct PlayerSettings;
+pub use settings::PlayerSettings;
 
 pub trait PlayerExtensions {
   fn add_player(&mut self, settings: PlayerSettings) -> &mut Self;
@@ -18,42 +36,171 @@ pub trait PlayerExtensions {
 
 impl PlayerExtensions for App {
   fn add_player(&mut self, settings: PlayerSettings) -> &mut Self {
+    let input_map = input::InputMap::load();
     self
       .add_event::<PlayerCommand>()
       .add_event::<PlayerControlCommand>()
-      .init_resource::<PlayerState>()
+      .add_event::<OutOfLives>()
+      .add_event::<replay::ReplayCommand>()
+      .add_event::<replay::ReplayRestart>()
+      .init_resource::<replay::ReplayState>()
+      .insert_resource(PlayerState::new(input_map.players.len(), settings.lives))
       .insert_resource(settings.clone())
+      .add_ron_asset::<PlayerSettings>()
+      .init_resource::<settings::PlayerSettingsHandle>()
+      .add_system(settings::load_settings)
+      .add_system(settings::apply_settings.after(settings::load_settings))
+      .insert_resource(input_map)
       .add_plugin(crosshair::CrosshairPlugin)
+      .add_system(hud::show_pickups)
+      .add_system(hud::show_objectives)
+      .add_system(
+        hud::update_hud
+          .after(hud::show_pickups)
+          .after(hud::show_objectives),
+      )
       .add_system(handle_cmd)
-      .add_system(read_input)
-      .add_system(handle_control_cmd.after(read_input))
-      .add_system(show_cotrails.after(handle_control_cmd))
+      .add_system(credit_kills.after(apply_damage))
+      .add_system(respawn.before(handle_cmd))
+      .add_system(
+        read_input
+          .in_set(PlayerControlSet)
+          .run_if(replay::not_playing),
+      )
+      .add_system(replay::read_input)
+      .add_system(replay::handle_cmd.after(replay::read_input))
+      .add_system(replay::record.after(read_input))
+      .add_system(replay::play.before(handle_control_cmd))
+      .add_system(replay::stop_on_unload)
+      .add_system(replay::sync_timestep.after(replay::stop_on_unload))
+      .on_level_active(replay::start)
+      .on_level_unloaded(hud::clear_notices)
+      .add_system(
+        handle_control_cmd
+          .in_set(PlayerControlSet)
+          .after(read_input)
+          .before(fly_ships),
+      )
+      .add_system(show_cotrails.after(update_boosts))
+      .add_system(input::save_input_map)
   }
 }
 
+/// Turns player input into ship commands, systems reading those commands run after it
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlayerControlSet;
+
+/// Index of a local player, also the index of its device in the input map
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PlayerId(pub usize);
+
+/// tints the crosshair and HUD of each player
+const PLAYER_COLORS: [Color; 4] = [
+  Color::WHITE,
+  Color::rgb(0.4, 0.8, 1.0),
+  Color::rgb(1.0, 0.6, 0.3),
+  Color::rgb(0.6, 1.0, 0.5),
+];
+
+/// distance between ships spawned at the same spawn point
+const PLAYER_SPACING: f32 = 15.0;
+
+fn player_color(player: PlayerId) -> Color {
+  PLAYER_COLORS[player.0 % PLAYER_COLORS.len()]
+}
+
 #[derive(Debug)]
 pub enum PlayerCommand {
-  Spawn,
-  Despawn,
+  /// Spawns a ship for every local player without one next to the first spawn point of the level,
+  /// without a loadout the ships are built from the player settings
+  Spawn(Option<ShipLoadout>),
+  /// Brings a player back after losing a ship
+  Respawn(PlayerId),
+  /// Removes the ship of a player and costs them a life
+  Despawn(PlayerId),
+  /// Removes all ships and restores all lives, the next active level spawns fresh ones
+  Reset,
 }
 
+/// Sent when every player has lost their last ship, lives are reset afterwards
 #[derive(Debug)]
+pub struct OutOfLives;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PlayerControlCommand {
-  Aim(Vec3),
-  Move(Vec3),
-  Fire,
-  CycleWeapon,
-  Shield,
+  Aim(PlayerId, Vec3),
+  Move(PlayerId, Vec3),
+  Fire(PlayerId),
+  CycleWeapon(PlayerId),
+  Shield(PlayerId),
+  Boost(PlayerId),
+}
+
+impl PlayerControlCommand {
+  pub fn player(&self) -> PlayerId {
+    match self {
+      PlayerControlCommand::Aim(player, _)
+      | PlayerControlCommand::Move(player, _)
+      | PlayerControlCommand::Fire(player)
+      | PlayerControlCommand::CycleWeapon(player)
+      | PlayerControlCommand::Shield(player)
+      | PlayerControlCommand::Boost(player) => *player,
+    }
+  }
 }
 
 #[derive(Component, Default)]
-struct PlayerComponent {
-  steering_pid: Vec3,
+pub struct PlayerComponent;
+
+/// Particle rate while flying normally, boosting multiplies it
+const COTRAIL_RATE: (f32, f32) = (200., 300.);
+const BOOST_COTRAIL_SCALE: f32 = 4.0;
+
+/// Marks the cotrail emitters of a ship, the boosted one only emits while boosting
+#[derive(Component)]
+struct Cotrail {
+  boosted: bool,
 }
 
 #[derive(Resource, Default)]
 struct PlayerState {
+  /// indexed by [`PlayerId`]
+  slots: Vec<PlayerSlot>,
+}
+
+impl PlayerState {
+  fn new(players: usize, lives: u32) -> Self {
+    Self {
+      slots: (0..players)
+        .map(|_| PlayerSlot {
+          lives,
+          ..default()
+        })
+        .collect(),
+    }
+  }
+}
+
+#[derive(Default)]
+struct PlayerSlot {
   current: Option<Entity>,
+  crosshair: Option<Entity>,
+  crosshair_ray: Option<Entity>,
+  raycast_plane: Option<Entity>,
+  hud: Option<Entity>,
+  lives: u32,
+  /// enemies destroyed by the ships of this player
+  kills: u32,
+  respawn: Option<Timer>,
+  /// reused when respawning
+  loadout: Option<ShipLoadout>,
+}
+
+impl PlayerSlot {
+  /// lost the last ship, the player sits out until lives are reset
+  fn is_out(&self) -> bool {
+    self.current.is_none() && self.respawn.is_none() && self.lives == 0
+  }
 }
 
 fn handle_cmd(
@@ -64,143 +211,231 @@ fn handle_cmd(
   asset_server: Res<AssetServer>,
   mut materials: ResMut<Assets<StandardMaterial>>,
   mut effects: ResMut<Assets<EffectAsset>>,
+  mut out_of_lives: EventWriter<OutOfLives>,
+  settings: Res<PlayerSettings>,
+  qry_spawn: Query<&Transform, With<SpawnPoint>>,
 ) {
+  let mut to_spawn = Vec::new();
   for evt in events.iter() {
-    match (evt, player_state.current) {
-      (PlayerCommand::Spawn, None) => {
-        let crosshair = asset_server.load("crosshair.png");
-
-        let mut color_gradient1 = Gradient::new();
-        color_gradient1.add_key(0.0, Vec4::new(0.0, 0.0, 8.0, 1.0));
-        color_gradient1.add_key(0.1, Vec4::new(8.0, 8.0, 8.0, 1.0));
-        color_gradient1.add_key(0.7, Vec4::new(8.0, 8.0, 0.0, 1.0));
-        color_gradient1.add_key(0.9, Vec4::new(8.0, 0.0, 0.0, 1.0));
-        color_gradient1.add_key(1.0, Vec4::new(8.0, 0.0, 0.0, 0.0));
-
-        let mut size_gradient1 = Gradient::new();
-        size_gradient1.add_key(0.0, Vec2::splat(0.8));
-        size_gradient1.add_key(0.3, Vec2::splat(0.5));
-        size_gradient1.add_key(1.0, Vec2::splat(0.2));
-
-        // Create the effect asset
-        let spawner = Spawner::rate(Value::Uniform((200., 300.)));
-        let effect = effects.add(
-          EffectAsset {
-            name: "cotrails".to_string(),
-            // Maximum number of particles alive at a time
-            capacity: 32768,
-            // Spawn at a rate of 5 particles per second
-            spawner,
-            ..Default::default()
+    match evt {
+      PlayerCommand::Spawn(loadout) => {
+        for (index, slot) in player_state.slots.iter_mut().enumerate() {
+          if slot.current.is_none() && !slot.is_out() && !to_spawn.contains(&PlayerId(index)) {
+            slot.loadout = loadout.clone();
+            to_spawn.push(PlayerId(index));
           }
-          // On spawn, randomly initialize the position of the particle
-          // to be over the surface of a sphere of radius 2 units.
-          .init(InitPositionCone3dModifier {
-            base_radius: 0.,
-            top_radius: 2.,
-            height: 10.,
-            dimension: ShapeDimension::Volume,
-          })
-          .init(InitVelocitySphereModifier {
-            center: Vec3::ZERO,
-            speed: 50.0.into(),
-          })
-          // Also initialize the total lifetime of the particle, that is
-          // the time for which it's simulated and rendered. This modifier
-          // is mandatory, otherwise the particles won't show up.
-          .init(InitLifetimeModifier {
-            // Give a bit of variation by randomizing the lifetime per particle
-            lifetime: Value::Uniform((0.8, 1.2)),
-          })
-          .init(InitAgeModifier {
-            // Give a bit of variation by randomizing the age per particle. This will control the
-            // starting color and starting size of particles.
-            age: Value::Uniform((0.0, 0.2)),
-          })
-          .update(LinearDragModifier { drag: 5. })
-          .render(BillboardModifier {})
-          // Render the particles with a color gradient over their
-          // lifetime. This maps the gradient key 0 to the particle spawn
-          // time, and the gradient key 1 to the particle death (here, 10s).
-          .render(ColorOverLifetimeModifier {
-            gradient: color_gradient1,
-          })
-          .render(SizeOverLifetimeModifier {
-            gradient: size_gradient1,
-          }),
-        );
-
-        let player = cmd
-          .spawn((
-            SceneBundle {
-              scene: asset_server.load("ship.gltf#Scene0"),
-              ..default()
-            },
-            PlayerComponent {
-              steering_pid: Vec3::new(1.0, 1.0, 1.0),
-            },
-            PidCameraTarget,
-          ))
-          .insert(GravityScale(0.0))
-          .insert(RigidBody::Dynamic)
-          .insert(Collider::ball(5.0))
-          .insert(LockedAxes::TRANSLATION_LOCKED_Y)
-          .insert(Damping {
-            linear_damping: 5.0,
-            angular_damping: 10.0,
-          })
-          .insert(Dominance::group(10))
-          .insert(ColliderMassProperties::Density(1.0))
-          .insert(ExternalImpulse {
-            impulse: Vec3::new(0.0, 0.0, 0.0),
-            torque_impulse: Vec3::new(0.0, 0.0, 0.0),
-          })
-          .with_children(|b| {
-            b.spawn((
-              Name::new("emit:cotrails"),
-              ParticleEffectBundle {
-                effect: ParticleEffect::new(effect),
-                transform: Transform::from_translation(Vec3::new(00., 0., 0.0))
-                  .with_rotation(Quat::from_rotation_x(-1.)),
-                ..Default::default()
-              }
-              .with_spawner(spawner),
-            ));
-          })
-          .id();
-
-        cmd.spawn((
-          ImageBundle {
-            style: Style {
-              position: UiRect::default(),
-              position_type: PositionType::Absolute,
-              size: Size::new(Val::Px(50.0), Val::Px(50.0)),
-              ..default()
-            },
-            image: UiImage::new(crosshair),
-            visibility: Visibility::Hidden,
-            ..default()
-          },
-          crosshair::Crosshair {
-            active: true,
+        }
+      }
+      PlayerCommand::Respawn(player) => match player_state.slots.get(player.0) {
+        Some(slot) if slot.current.is_none() && !to_spawn.contains(player) => {
+          to_spawn.push(*player);
+        }
+        _ => warn!("Invalid player command {:?}", evt),
+      },
+      PlayerCommand::Despawn(player) => {
+        let Some(slot) = player_state.slots.get_mut(player.0).filter(|s| s.current.is_some())
+        else {
+          warn!("Invalid player command {:?}", evt);
+          continue;
+        };
+        despawn_player(&mut cmd, slot);
+        if slot.lives > 0 {
+          slot.lives -= 1;
+          info!("player {} lost a ship, {} lives left", player.0 + 1, slot.lives);
+          slot.respawn = Some(Timer::from_seconds(settings.respawn_delay, TimerMode::Once));
+        } else {
+          info!("player {} is out of lives", player.0 + 1);
+        }
+
+        if player_state.slots.iter().all(PlayerSlot::is_out) {
+          info!("out of lives");
+          for slot in player_state.slots.iter_mut() {
+            slot.lives = settings.lives;
+          }
+          out_of_lives.send(OutOfLives);
+        }
+      }
+      PlayerCommand::Reset => {
+        for slot in player_state.slots.iter_mut() {
+          despawn_player(&mut cmd, slot);
+          slot.lives = settings.lives;
+          slot.kills = 0;
+          slot.respawn = None;
+        }
+      }
+    }
+  }
+
+  for player in to_spawn {
+    let slot = &mut player_state.slots[player.0];
+    let ship = slot.loadout.clone().unwrap_or_else(|| settings.loadout());
+
+    let crosshair = asset_server.load("crosshair.png");
+
+    let mut color_gradient1 = Gradient::new();
+    color_gradient1.add_key(0.0, Vec4::new(0.0, 0.0, 8.0, 1.0));
+    color_gradient1.add_key(0.1, Vec4::new(8.0, 8.0, 8.0, 1.0));
+    color_gradient1.add_key(0.7, Vec4::new(8.0, 8.0, 0.0, 1.0));
+    color_gradient1.add_key(0.9, Vec4::new(8.0, 0.0, 0.0, 1.0));
+    color_gradient1.add_key(1.0, Vec4::new(8.0, 0.0, 0.0, 0.0));
+
+    // hotter and bluer while boosting
+    let mut boost_gradient = Gradient::new();
+    boost_gradient.add_key(0.0, Vec4::new(4.0, 4.0, 16.0, 1.0));
+    boost_gradient.add_key(0.1, Vec4::new(16.0, 16.0, 16.0, 1.0));
+    boost_gradient.add_key(0.7, Vec4::new(4.0, 8.0, 16.0, 1.0));
+    boost_gradient.add_key(0.9, Vec4::new(0.0, 0.0, 16.0, 1.0));
+    boost_gradient.add_key(1.0, Vec4::new(0.0, 0.0, 16.0, 0.0));
+
+    let cotrail = effects.add(cotrail_effect(color_gradient1, cotrail_spawner(false)));
+    let boost_cotrail = effects.add(cotrail_effect(boost_gradient, cotrail_spawner(true)));
+
+    let mut transform = qry_spawn.iter().next().copied().unwrap_or_default();
+    // spread the ships out so they don't spawn inside each other
+    transform.translation += transform.rotation * Vec3::X * PLAYER_SPACING * player.0 as f32;
+
+    let ship_entity = cmd
+      .spawn((
+        SceneBundle {
+          scene: asset_server.load(ship.model.as_str()),
+          transform,
+          ..default()
+        },
+        PlayerComponent,
+        player,
+        PidCameraTarget,
+        Weapons::new(ship.weapons.clone()),
+        Shield::from(ship.shield),
+        Boost::from(ship.boost),
+        Health::new(ship.health),
+        ImpactDamage::default(),
+        Invulnerable::new(settings.invulnerability),
+        ship.engine.clone(),
+        ShipInput::default(),
+        Modifiers::default(),
+      ))
+      .insert(GravityScale(0.0))
+      .insert(RigidBody::Dynamic)
+      .insert(Collider::ball(settings.collider_radius))
+      .insert(ActiveEvents::CONTACT_FORCE_EVENTS)
+      .insert(ContactForceEventThreshold(ImpactDamage::default().threshold))
+      .insert(LockedAxes::TRANSLATION_LOCKED_Y)
+      .insert(Damping {
+        linear_damping: settings.linear_damping,
+        angular_damping: settings.angular_damping,
+      })
+      .insert(Dominance::group(10))
+      .insert(ColliderMassProperties::Density(settings.density))
+      .insert(Velocity::default())
+      .insert(ExternalImpulse {
+        impulse: Vec3::new(0.0, 0.0, 0.0),
+        torque_impulse: Vec3::new(0.0, 0.0, 0.0),
+      })
+      .with_children(|b| {
+        for (name, effect, boosted) in [
+          ("emit:cotrails", cotrail, false),
+          ("emit:boost-cotrails", boost_cotrail, true),
+        ] {
+          b.spawn((
+            Name::new(name),
+            ParticleEffectBundle {
+              effect: ParticleEffect::new(effect),
+              transform: Transform::from_translation(Vec3::new(00., 0., 0.0))
+                .with_rotation(Quat::from_rotation_x(-1.)),
+              ..Default::default()
+            }
+            .with_spawner(cotrail_spawner(boosted).with_active(!boosted)),
+            Cotrail { boosted },
+          ));
+        }
+        b.spawn((
+          Name::new("turret"),
+          PbrBundle {
+            mesh: meshes.add(shape::Cube { size: 1.5 }.into()),
+            material: materials.add(Color::rgb(0.6, 0.6, 0.7).into()),
+            transform: Transform::from_xyz(0.0, 2.0, 0.0),
             ..default()
           },
+          Turret::from(ship.turret),
         ));
+      })
+      .id();
 
-        cmd.spawn((
-          PbrBundle {
-            mesh: meshes.add(Mesh::try_from(shape::Plane::from_size(1000000.)).unwrap()),
-            material: materials.add(Color::rgba(1.0, 1.0, 1.0, 0.0).into()),
-            ..Default::default()
+    let crosshair_ui = cmd
+      .spawn((
+        ImageBundle {
+          style: Style {
+            position: UiRect::default(),
+            position_type: PositionType::Absolute,
+            size: Size::new(Val::Px(50.0), Val::Px(50.0)),
+            ..default()
           },
-          RaycastMesh::<crosshair::CrosshairRaycastSet>::default(),
-        ));
+          image: UiImage::new(crosshair),
+          visibility: Visibility::Hidden,
+          background_color: player_color(player).into(),
+          ..default()
+        },
+        crosshair::Crosshair {
+          active: true,
+          ..default()
+        },
+        player,
+      ))
+      .id();
 
-        player_state.current = Some(player);
-      }
-      _ => {
-        warn!("Invalid player command {:?}", evt);
-      }
+    let crosshair_ray = cmd.spawn(crosshair::crosshair_ray(player)).id();
+
+    let raycast_plane = cmd
+      .spawn((
+        PbrBundle {
+          mesh: meshes.add(Mesh::try_from(shape::Plane::from_size(1000000.)).unwrap()),
+          material: materials.add(Color::rgba(1.0, 1.0, 1.0, 0.0).into()),
+          ..Default::default()
+        },
+        RaycastMesh::<crosshair::CrosshairRaycastSet>::default(),
+      ))
+      .id();
+
+    let hud = hud::spawn_hud(&mut cmd, &asset_server, player);
+
+    slot.current = Some(ship_entity);
+    slot.crosshair = Some(crosshair_ui);
+    slot.crosshair_ray = Some(crosshair_ray);
+    slot.raycast_plane = Some(raycast_plane);
+    slot.hud = Some(hud);
+    slot.respawn = None;
+  }
+}
+
+/// Removes the ship along with its crosshair, raycast plane and HUD
+fn despawn_player(cmd: &mut Commands, slot: &mut PlayerSlot) {
+  for entity in [
+    slot.current.take(),
+    slot.crosshair.take(),
+    slot.crosshair_ray.take(),
+    slot.raycast_plane.take(),
+    slot.hud.take(),
+  ]
+  .into_iter()
+  .flatten()
+  {
+    cmd.entity(entity).despawn_recursive();
+  }
+}
+
+fn respawn(
+  mut player_state: ResMut<PlayerState>,
+  mut player_cmd: EventWriter<PlayerCommand>,
+  config: Res<RapierConfiguration>,
+  time: Res<Time>,
+) {
+  let dt = Duration::from_secs_f32(physics_dt(&config, &time));
+  for (index, slot) in player_state.slots.iter_mut().enumerate() {
+    let Some(timer) = slot.respawn.as_mut() else {
+      continue;
+    };
+    if timer.tick(dt).just_finished() {
+      player_cmd.send(PlayerCommand::Respawn(PlayerId(index)));
     }
   }
 }
@@ -208,95 +443,185 @@ fn handle_cmd(
 fn handle_control_cmd(
   mut events: EventReader<PlayerControlCommand>,
   player_state: Res<PlayerState>,
-  mut qry: Query<(&Transform, &PlayerComponent, &mut ExternalImpulse)>,
-  time: Res<Time>,
+  mut qry: Query<&mut ShipInput>,
+  mut weapon_cmd: EventWriter<WeaponCommand>,
+  mut shield_cmd: EventWriter<ShieldCommand>,
+  mut boost_cmd: EventWriter<BoostCommand>,
+  mut turret_cmd: EventWriter<TurretCommand>,
 ) {
-  if let Some(player) = player_state.current {
-    if let Ok((player_transform, player, mut impulse)) = qry.get_mut(player) {
-      for evt in events.iter() {
-        match evt {
-          PlayerControlCommand::Move(dir) => {
-            // todo: create smoothing fn for impulses
-            // let max_linear_impulse = 2000.0;
-            let max_angular_impulse = 1000.0;
-            let multiplier = 2000.0;
-
-            let dir2d = dir.xz().normalize();
-            let orientation = (player_transform.rotation * Vec3::Z).normalize();
-            let orientation2d = orientation.xz().normalize();
-            let error_radians = dir2d.angle_between(orientation2d);
-            let error = error_radians.to_degrees();
-            let p = error * player.steering_pid.x;
-            if error > 135.0 && error < 225.0 {
-              //impulse.impulse = orientation * -1.0 * multiplier;
-            } else {
-              impulse.impulse = orientation * multiplier;
-              if error < 135.0 {
-                impulse.torque_impulse = Vec3::Y * multiplier * error_radians;
-              } else {
-                impulse.torque_impulse = Vec3::NEG_Y * multiplier * error_radians;
-              }
-            }
-
-            //impulse.impulse = *dir * multiplier;
-          }
-          PlayerControlCommand::Aim(new_pos) => {
-            let o = *new_pos - player_transform.translation;
-            let t = player_transform.translation - o;
-            //player_transform.look_at(t, Vec3::Y);
-          }
-          _ => {
-            warn!("unsupported player cmd {:?}", evt);
-          }
-        }
+  for evt in events.iter() {
+    let Some(entity) = player_state.slots.get(evt.player().0).and_then(|s| s.current) else {
+      continue;
+    };
+    let Ok(mut ship_input) = qry.get_mut(entity) else {
+      continue;
+    };
+    match evt {
+      PlayerControlCommand::Move(_, dir) => ship_input.0 = *dir,
+      PlayerControlCommand::Aim(_, new_pos) => {
+        turret_cmd.send(TurretCommand::Aim(entity, *new_pos));
       }
+      PlayerControlCommand::Fire(_) => weapon_cmd.send(WeaponCommand::Fire(entity)),
+      PlayerControlCommand::CycleWeapon(_) => weapon_cmd.send(WeaponCommand::Cycle(entity)),
+      PlayerControlCommand::Shield(_) => shield_cmd.send(ShieldCommand(entity)),
+      PlayerControlCommand::Boost(_) => boost_cmd.send(BoostCommand(entity)),
+    }
+  }
+}
+
+fn credit_kills(
+  mut events: EventReader<Destroyed>,
+  mut player_state: ResMut<PlayerState>,
+  qry_enemy: Query<(), With<LevelEnemy>>,
+  qry_ship: Query<&PlayerId, With<PlayerComponent>>,
+) {
+  for evt in events.iter() {
+    if !qry_enemy.contains(evt.entity) {
+      continue;
+    }
+    let Some(player) = evt.by.and_then(|by| qry_ship.get(by).ok()) else {
+      continue;
+    };
+    if let Some(slot) = player_state.slots.get_mut(player.0) {
+      slot.kills += 1;
     }
   }
 }
 
 fn read_input(
-  keyboard_input: Res<Input<KeyCode>>,
+  input: ActionInput,
   mut evts: EventWriter<PlayerControlCommand>,
-  qry_crosshair: Query<&crosshair::Crosshair, Changed<crosshair::Crosshair>>,
+  qry_crosshair: Query<(&PlayerId, &crosshair::Crosshair), Changed<crosshair::Crosshair>>,
+  qry_cursor: Query<(&PlayerId, &crosshair::Crosshair)>,
 ) {
-  let mut move_vec = Vec3::default();
+  for player in input.players() {
+    let input = input.player(player);
+    // mouse clicks grab the cursor until it is grabbed
+    let cursor_free =
+      input.uses_mouse() && !qry_cursor.iter().any(|(id, c)| *id == player && c.active);
+    let mut move_vec = stick_to_world(input.move_stick());
 
-  if keyboard_input.pressed(KeyCode::W) {
-    move_vec += Vec3::Z;
-  }
-  if keyboard_input.pressed(KeyCode::A) {
-    move_vec += Vec3::X;
-  }
-  if keyboard_input.pressed(KeyCode::S) {
-    move_vec += Vec3::NEG_Z;
-  }
-  if keyboard_input.pressed(KeyCode::D) {
-    move_vec += Vec3::NEG_X;
-  }
+    if input.pressed(Action::MoveForward) {
+      move_vec += Vec3::Z;
+    }
+    if input.pressed(Action::MoveLeft) {
+      move_vec += Vec3::X;
+    }
+    if input.pressed(Action::MoveBack) {
+      move_vec += Vec3::NEG_Z;
+    }
+    if input.pressed(Action::MoveRight) {
+      move_vec += Vec3::NEG_X;
+    }
 
-  if move_vec.length() > 0.0 {
-    evts.send(PlayerControlCommand::Move(move_vec.normalize()));
+    if move_vec.length() > 0.0 {
+      evts.send(PlayerControlCommand::Move(player, move_vec.normalize()));
+    }
+
+    let fire = if cursor_free {
+      input.pressed_without_mouse(Action::Fire)
+    } else {
+      input.pressed(Action::Fire)
+    };
+    if fire {
+      evts.send(PlayerControlCommand::Fire(player));
+    }
+    if input.pressed(Action::Shield) {
+      evts.send(PlayerControlCommand::Shield(player));
+    }
+    if input.just_pressed(Action::Boost) {
+      evts.send(PlayerControlCommand::Boost(player));
+    }
+    if input.just_pressed(Action::CycleWeapon) {
+      evts.send(PlayerControlCommand::CycleWeapon(player));
+    }
   }
 
-  for c in qry_crosshair.iter() {
+  for (player, c) in qry_crosshair.iter() {
     if let Some(word_pos) = c.world_pos {
-      evts.send(PlayerControlCommand::Aim(word_pos));
+      evts.send(PlayerControlCommand::Aim(*player, word_pos));
     }
   }
 }
 
+/// Maps stick directions to the same world directions as W/A/S/D
+fn stick_to_world(stick: Vec2) -> Vec3 {
+  Vec3::new(-stick.x, 0.0, stick.y)
+}
+
 fn show_cotrails(
-  qry: Query<(&ExternalImpulse, &Children)>,
-  mut qry_effect: Query<&mut ParticleEffect>,
+  qry: Query<(&ExternalImpulse, Option<&Boost>, &Children)>,
+  mut qry_effect: Query<(&mut ParticleEffect, &Cotrail)>,
 ) {
-  for (impulse, children) in qry.iter() {
+  for (impulse, boost, children) in qry.iter() {
+    let boosted = boost.map_or(false, Boost::active);
+    let moving = impulse.impulse.length() > 0.1;
     for child in children {
-      if let Ok(mut effect) = qry_effect.get_mut(*child) {
-        effect
-          .maybe_spawner()
-          .unwrap()
-          .set_active(impulse.impulse.length() > 0.1);
-      }
+      let Ok((mut effect, cotrail)) = qry_effect.get_mut(*child) else {
+        continue;
+      };
+      // not there until the effect has been compiled
+      let Some(spawner) = effect.maybe_spawner() else {
+        continue;
+      };
+      spawner.set_active(moving && cotrail.boosted == boosted);
     }
   }
 }
+
+/// Cotrail particles with the given colours over their lifetime
+fn cotrail_effect(color_gradient: Gradient<Vec4>, spawner: Spawner) -> EffectAsset {
+  let mut size_gradient1 = Gradient::new();
+  size_gradient1.add_key(0.0, Vec2::splat(0.8));
+  size_gradient1.add_key(0.3, Vec2::splat(0.5));
+  size_gradient1.add_key(1.0, Vec2::splat(0.2));
+
+  EffectAsset {
+    name: "cotrails".to_string(),
+    // Maximum number of particles alive at a time
+    capacity: 32768,
+    // Spawn at a rate of 5 particles per second
+    spawner,
+    ..Default::default()
+  }
+  // On spawn, randomly initialize the position of the particle
+  // to be over the surface of a sphere of radius 2 units.
+  .init(InitPositionCone3dModifier {
+    base_radius: 0.,
+    top_radius: 2.,
+    height: 10.,
+    dimension: ShapeDimension::Volume,
+  })
+  .init(InitVelocitySphereModifier {
+    center: Vec3::ZERO,
+    speed: 50.0.into(),
+  })
+  // Also initialize the total lifetime of the particle, that is
+  // the time for which it's simulated and rendered. This modifier
+  // is mandatory, otherwise the particles won't show up.
+  .init(InitLifetimeModifier {
+    // Give a bit of variation by randomizing the lifetime per particle
+    lifetime: Value::Uniform((0.8, 1.2)),
+  })
+  .init(InitAgeModifier {
+    // Give a bit of variation by randomizing the age per particle. This will control the
+    // starting color and starting size of particles.
+    age: Value::Uniform((0.0, 0.2)),
+  })
+  .update(LinearDragModifier { drag: 5. })
+  .render(BillboardModifier {})
+  // Render the particles with a color gradient over their
+  // lifetime. This maps the gradient key 0 to the particle spawn
+  // time, and the gradient key 1 to the particle death (here, 10s).
+  .render(ColorOverLifetimeModifier {
+    gradient: color_gradient,
+  })
+  .render(SizeOverLifetimeModifier {
+    gradient: size_gradient1,
+  })
+}
+
+fn cotrail_spawner(boosted: bool) -> Spawner {
+  let scale = if boosted { BOOST_COTRAIL_SCALE } else { 1.0 };
+  Spawner::rate(Value::Uniform((COTRAIL_RATE.0 * scale, COTRAIL_RATE.1 * scale)))
+}
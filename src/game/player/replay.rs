@@ -0,0 +1,383 @@
+use std::{
+  path::{Path, PathBuf},
+  time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+  input::{Action, ActionInput},
+  PlayerControlCommand,
+};
+use crate::game::{
+  level::{LevelState, LevelUnloaded},
+  pickups::PickupRng,
+  weapons::WeaponRng,
+};
+
+/// where recordings are written to and played back from by default
+pub const LAST_REPLAY_PATH: &str = "replays/last.replay.ron";
+/// physics step while recording or playing, so replays don't depend on the frame rate
+const REPLAY_TIMESTEP: f32 = 1.0 / 60.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+  /// seeds everything random that player commands depend on, like weapon spread and drops
+  pub seed: u64,
+  pub level_id: u64,
+  pub frames: Vec<ReplayFrame>,
+}
+
+/// Commands sent in one frame, frames without commands are not stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayFrame {
+  /// frames since the level became active
+  pub frame: u64,
+  /// simulated seconds since the level became active, playback goes by `frame`
+  pub time: f32,
+  pub commands: Vec<PlayerControlCommand>,
+}
+
+#[derive(Debug)]
+pub enum ReplayCommand {
+  Record,
+  Play(PathBuf),
+  /// stops recording or playback, recordings are saved to [`LAST_REPLAY_PATH`]
+  Stop,
+}
+
+/// Sent when the level has to restart so that recording or playback starts from a fresh level
+#[derive(Debug)]
+pub struct ReplayRestart {
+  pub level_id: u64,
+}
+
+#[derive(Resource, Default)]
+pub enum ReplayState {
+  #[default]
+  Idle,
+  /// waiting for the level to restart
+  Pending { replay: Replay, record: bool },
+  Recording { replay: Replay, frame: u64, time: f32 },
+  Playing { replay: Replay, frame: u64, next: usize },
+}
+
+impl ReplayState {
+  pub fn is_playing(&self) -> bool {
+    matches!(self, ReplayState::Playing { .. } | ReplayState::Pending { record: false, .. })
+  }
+}
+
+pub(super) fn not_playing(state: Res<ReplayState>) -> bool {
+  !state.is_playing()
+}
+
+pub(super) fn read_input(
+  input: ActionInput,
+  state: Res<ReplayState>,
+  mut evts: EventWriter<ReplayCommand>,
+) {
+  if input.just_pressed(Action::ToggleRecording) {
+    evts.send(match *state {
+      ReplayState::Recording { .. } => ReplayCommand::Stop,
+      _ => ReplayCommand::Record,
+    });
+  }
+  if input.just_pressed(Action::PlayReplay) {
+    evts.send(match *state {
+      ReplayState::Playing { .. } => ReplayCommand::Stop,
+      _ => ReplayCommand::Play(PathBuf::from(LAST_REPLAY_PATH)),
+    });
+  }
+}
+
+pub(super) fn handle_cmd(
+  mut events: EventReader<ReplayCommand>,
+  mut state: ResMut<ReplayState>,
+  mut restart: EventWriter<ReplayRestart>,
+  level_state: Res<LevelState>,
+) {
+  for evt in events.iter() {
+    match evt {
+      ReplayCommand::Record => {
+        let LevelState::Active(level_id) = *level_state else {
+          warn!("cannot record without an active level");
+          continue;
+        };
+        let seed = SystemTime::now()
+          .duration_since(UNIX_EPOCH)
+          .map_or(0, |d| d.as_nanos() as u64);
+        *state = ReplayState::Pending {
+          replay: Replay {
+            seed,
+            level_id,
+            frames: Vec::new(),
+          },
+          record: true,
+        };
+        restart.send(ReplayRestart { level_id });
+      }
+      ReplayCommand::Play(path) => match load_replay(path) {
+        Ok(replay) => {
+          info!("playing replay {}", path.display());
+          restart.send(ReplayRestart {
+            level_id: replay.level_id,
+          });
+          *state = ReplayState::Pending {
+            replay,
+            record: false,
+          };
+        }
+        Err(e) => warn!("cannot read replay {}: {}", path.display(), e),
+      },
+      ReplayCommand::Stop => {
+        if let ReplayState::Recording { replay, .. } = &*state {
+          save_replay(Path::new(LAST_REPLAY_PATH), replay);
+        }
+        *state = ReplayState::Idle;
+      }
+    }
+  }
+}
+
+/// Runs once the restarted level is active
+pub(super) fn start(
+  mut state: ResMut<ReplayState>,
+  mut rng: ResMut<WeaponRng>,
+  mut pickup_rng: ResMut<PickupRng>,
+) {
+  let ReplayState::Pending { replay, record } = &*state else {
+    return;
+  };
+  info!("replay of level {} started", replay.level_id);
+  rng.reseed(replay.seed);
+  pickup_rng.reseed(replay.seed);
+  let (replay, record) = (replay.clone(), *record);
+  *state = if record {
+    ReplayState::Recording {
+      replay,
+      frame: 0,
+      time: 0.0,
+    }
+  } else {
+    ReplayState::Playing {
+      replay,
+      frame: 0,
+      next: 0,
+    }
+  };
+}
+
+pub(super) fn record(
+  mut state: ResMut<ReplayState>,
+  mut events: EventReader<PlayerControlCommand>,
+) {
+  let ReplayState::Recording { replay, frame, time: elapsed } = &mut *state else {
+    events.clear();
+    return;
+  };
+  let commands: Vec<PlayerControlCommand> = events.iter().cloned().collect();
+  if !commands.is_empty() {
+    replay.frames.push(ReplayFrame {
+      frame: *frame,
+      time: *elapsed,
+      commands,
+    });
+  }
+  *frame += 1;
+  *elapsed += REPLAY_TIMESTEP;
+}
+
+pub(super) fn play(mut state: ResMut<ReplayState>, mut evts: EventWriter<PlayerControlCommand>) {
+  let ReplayState::Playing { replay, frame, next } = &mut *state else {
+    return;
+  };
+  while let Some(recorded) = replay.frames.get(*next).filter(|f| f.frame <= *frame) {
+    evts.send_batch(recorded.commands.iter().cloned());
+    *next += 1;
+  }
+  *frame += 1;
+
+  if *next >= replay.frames.len() {
+    info!("replay finished");
+    *state = ReplayState::Idle;
+  }
+}
+
+/// Ends recording or playback once its level goes away, like after winning it
+pub(super) fn stop_on_unload(
+  mut events: EventReader<LevelUnloaded>,
+  mut state: ResMut<ReplayState>,
+) {
+  for evt in events.iter() {
+    match &*state {
+      ReplayState::Recording { replay, .. } if replay.level_id == evt.0 => {
+        save_replay(Path::new(LAST_REPLAY_PATH), replay);
+      }
+      ReplayState::Playing { replay, .. } if replay.level_id == evt.0 => {
+        info!("replay stopped, level {} was unloaded", evt.0);
+      }
+      _ => continue,
+    }
+    *state = ReplayState::Idle;
+  }
+}
+
+/// Steps the physics by a fixed amount per frame while recording or playing, recorded frames then
+/// line up with the same physics steps on playback
+pub(super) fn sync_timestep(state: Res<ReplayState>, mut config: ResMut<RapierConfiguration>) {
+  if !state.is_changed() {
+    return;
+  }
+  let timestep_mode = match *state {
+    ReplayState::Recording { .. } | ReplayState::Playing { .. } => TimestepMode::Fixed {
+      dt: REPLAY_TIMESTEP,
+      substeps: 1,
+    },
+    _ => RapierConfiguration::default().timestep_mode,
+  };
+  if config.timestep_mode != timestep_mode {
+    config.timestep_mode = timestep_mode;
+  }
+}
+
+fn load_replay(path: &Path) -> Result<Replay, String> {
+  let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+  ron::de::from_bytes(&bytes).map_err(|e| e.to_string())
+}
+
+fn save_replay(path: &Path, replay: &Replay) {
+  let saved = ron::ser::to_string(replay)
+    .map_err(|e| e.to_string())
+    .and_then(|data| {
+      if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+      }
+      std::fs::write(path, data).map_err(|e| e.to_string())
+    });
+  match saved {
+    Ok(()) => info!("saved replay to {}", path.display()),
+    Err(e) => warn!("cannot save replay to {}: {}", path.display(), e),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::{Duration, Instant};
+
+  use utils::{
+    level::ObjectiveDef,
+    loadout::TurretDef,
+    pickup::{ModifierDef, Stacking, Stat},
+  };
+
+  use super::*;
+  use crate::game::{
+    health::{HealthPlugin, Invulnerable},
+    level::LevelObjectives,
+    modifiers::{Modifiers, ModifiersPlugin},
+    objectives::{ObjectiveStatus, Objectives, ObjectivesPlugin},
+    player::PlayerCommand,
+    shield::{Shield, ShieldCommand, ShieldPlugin},
+    turret::{Turret, TurretCommand, TurretPlugin},
+  };
+
+  const FRAMES: u32 = 120;
+
+  #[derive(Debug, PartialEq)]
+  struct Snapshot {
+    shield: f32,
+    shield_up: bool,
+    speed: f32,
+    invulnerable: bool,
+    turret: Quat,
+    survived: ObjectiveStatus,
+  }
+
+  /// Runs the gameplay timers as during a replay, with frames taking `frame_time` seconds
+  fn run(frame_time: f32) -> Vec<Snapshot> {
+    let mut app = App::new();
+    app
+      .insert_resource(RapierConfiguration {
+        timestep_mode: TimestepMode::Fixed {
+          dt: REPLAY_TIMESTEP,
+          substeps: 1,
+        },
+        ..default()
+      })
+      .init_resource::<Time>()
+      .add_event::<CollisionEvent>()
+      .add_event::<ContactForceEvent>()
+      .add_event::<PlayerCommand>()
+      .add_plugin(ShieldPlugin)
+      .add_plugin(ModifiersPlugin)
+      .add_plugin(HealthPlugin)
+      .add_plugin(TurretPlugin)
+      .add_plugin(ObjectivesPlugin)
+      .insert_resource(LevelObjectives(vec![ObjectiveDef::Survive { seconds: 1.2 }]));
+
+    let mut modifiers = Modifiers::default();
+    modifiers.add(ModifierDef {
+      stat: Stat::Speed,
+      factor: 2.0,
+      seconds: 1.0,
+      stacking: Stacking::Refresh,
+    });
+    let mut turret = None;
+    let ship = app
+      .world
+      .spawn((
+        TransformBundle::default(),
+        Shield::default(),
+        modifiers,
+        Invulnerable::new(0.8),
+      ))
+      .with_children(|b| {
+        let slow = Turret::from(TurretDef {
+          traverse_speed: 1.0,
+          ..default()
+        });
+        turret = Some(b.spawn((slow, Transform::default())).id());
+      })
+      .id();
+    let turret = turret.unwrap();
+    app.world.send_event(TurretCommand::Aim(ship, Vec3::new(0.0, 0.0, -100.0)));
+
+    let start = Instant::now();
+    let mut snapshots = Vec::new();
+    for frame in 0..FRAMES {
+      let now = start + Duration::from_secs_f32(frame as f32 * frame_time);
+      app.world.resource_mut::<Time>().update_with_instant(now);
+      if frame < 40 {
+        app.world.send_event(ShieldCommand(ship));
+      }
+      app.update();
+
+      let shield = app.world.get::<Shield>(ship).unwrap();
+      snapshots.push(Snapshot {
+        shield: shield.energy,
+        shield_up: shield.active(),
+        speed: app.world.get::<Modifiers>(ship).unwrap().factor(Stat::Speed),
+        invulnerable: app.world.get::<Invulnerable>(ship).is_some(),
+        turret: app.world.get::<Transform>(turret).unwrap().rotation,
+        survived: app.world.resource::<Objectives>().progress[0].status,
+      });
+    }
+    snapshots
+  }
+
+  #[test]
+  fn timers_ignore_the_frame_rate() {
+    let slow = run(1.0 / 24.0);
+    let fast = run(1.0 / 144.0);
+    assert_eq!(slow, fast);
+
+    // everything timed ran out within the recorded frames
+    let last = slow.last().unwrap();
+    assert!(!last.invulnerable);
+    assert_eq!(last.speed, 1.0);
+    assert_eq!(last.survived, ObjectiveStatus::Completed);
+  }
+}
@@ -0,0 +1,144 @@
+use bevy::{prelude::*, reflect::TypeUuid};
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+use utils::{
+  loadout::{BoostDef, ShieldDef, ShipLoadout, TurretDef},
+  ron_asset::RonAsset,
+  ship::Spaceship,
+};
+
+use super::{PlayerId, PlayerState};
+
+/// tuning file that replaces the settings passed to `add_player` once it is loaded
+pub const PLAYER_SETTINGS_PATH: &str = "default.player.ron";
+
+#[derive(Resource, Debug, Clone, Deserialize, TypeUuid)]
+#[uuid = "7c1e2f40-9a3b-4f55-b8d2-3e6a0c9d41b7"]
+#[serde(default)]
+pub struct PlayerSettings {
+  pub lives: u32,
+  /// seconds between losing the ship and respawning
+  pub respawn_delay: f32,
+  /// seconds the ship can't take damage after spawning
+  pub invulnerability: f32,
+  pub health: f32,
+  /// only used for ships spawned after a change
+  pub model: String,
+  pub collider_radius: f32,
+  pub density: f32,
+  pub linear_damping: f32,
+  pub angular_damping: f32,
+  pub ship: Spaceship,
+  pub weapons: Vec<String>,
+  pub shield: ShieldDef,
+  pub boost: BoostDef,
+  pub turret: TurretDef,
+}
+
+impl Default for PlayerSettings {
+  fn default() -> Self {
+    Self {
+      lives: 3,
+      respawn_delay: 2.0,
+      invulnerability: 3.0,
+      health: 100.0,
+      model: "ship.gltf#Scene0".to_string(),
+      collider_radius: 5.0,
+      density: 1.0,
+      linear_damping: 5.0,
+      angular_damping: 10.0,
+      ship: default(),
+      weapons: vec!["blaster".to_string(), "scatter".to_string()],
+      shield: default(),
+      boost: default(),
+      turret: default(),
+    }
+  }
+}
+
+impl PlayerSettings {
+  /// Loadout used when none was picked in the hangar
+  pub fn loadout(&self) -> ShipLoadout {
+    ShipLoadout {
+      name: "default".to_string(),
+      model: self.model.clone(),
+      health: self.health,
+      engine: self.ship.clone(),
+      weapons: self.weapons.clone(),
+      shield: self.shield,
+      boost: self.boost,
+      turret: self.turret,
+    }
+  }
+}
+
+impl RonAsset for PlayerSettings {
+  const EXTENSIONS: &'static [&'static str] = &["player.ron"];
+}
+
+#[derive(Resource)]
+pub(super) struct PlayerSettingsHandle(Handle<PlayerSettings>);
+
+impl FromWorld for PlayerSettingsHandle {
+  fn from_world(world: &mut World) -> Self {
+    Self(world.resource::<AssetServer>().load(PLAYER_SETTINGS_PATH))
+  }
+}
+
+/// Copies the settings file into the resource whenever it is loaded or changed on disk, the
+/// players start with the lives from the file once it is first loaded
+pub(super) fn load_settings(
+  mut cmd: Commands,
+  mut events: EventReader<AssetEvent<PlayerSettings>>,
+  mut player_state: ResMut<PlayerState>,
+  handle: Res<PlayerSettingsHandle>,
+  assets: Res<Assets<PlayerSettings>>,
+) {
+  for evt in events.iter() {
+    let (AssetEvent::Created { handle: h } | AssetEvent::Modified { handle: h }) = evt else {
+      continue;
+    };
+    let Some(settings) = assets.get(h).filter(|_| *h == handle.0) else {
+      continue;
+    };
+    info!("loaded {}", PLAYER_SETTINGS_PATH);
+    cmd.insert_resource(settings.clone());
+
+    if matches!(evt, AssetEvent::Created { .. }) {
+      if player_state.slots.iter().all(|s| s.current.is_none()) {
+        *player_state = PlayerState::new(player_state.slots.len(), settings.lives);
+      } else {
+        warn!("{} loaded after spawning, lives apply once they are reset", PLAYER_SETTINGS_PATH);
+      }
+    }
+  }
+}
+
+/// Retunes the current ship so changes can be tried without respawning, a loadout picked in the
+/// hangar keeps its own engine
+pub(super) fn apply_settings(
+  settings: Res<PlayerSettings>,
+  player_state: Res<PlayerState>,
+  mut qry: Query<(
+    &PlayerId,
+    &mut Spaceship,
+    &mut Collider,
+    &mut ColliderMassProperties,
+    &mut Damping,
+  )>,
+) {
+  if !settings.is_changed() {
+    return;
+  }
+  for (id, mut ship, mut collider, mut mass, mut damping) in qry.iter_mut() {
+    if player_state.slots.get(id.0).map_or(true, |s| s.loadout.is_none()) {
+      *ship = settings.ship.clone();
+    }
+    *collider = Collider::ball(settings.collider_radius);
+    *mass = ColliderMassProperties::Density(settings.density);
+    *damping = Damping {
+      linear_damping: settings.linear_damping,
+      angular_damping: settings.angular_damping,
+    };
+  }
+}
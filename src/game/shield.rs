@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use utils::loadout::ShieldDef;
+
+use super::{player::PlayerControlSet, ship::physics_dt};
+
+pub struct ShieldPlugin;
+impl Plugin for ShieldPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .add_event::<ShieldCommand>()
+      .add_event::<ShieldEvent>()
+      // the shield is only held while commands arrive in the same frame
+      .add_system(handle_cmd.after(PlayerControlSet))
+      .add_system(update_shields.after(handle_cmd));
+  }
+}
+
+#[derive(Component, Clone)]
+pub struct Shield {
+  pub energy: f32,
+  pub max_energy: f32,
+  /// energy spent per second while the shield is up
+  pub drain: f32,
+  /// energy regained per second while the shield is down
+  pub recharge_rate: f32,
+  /// seconds after the shield went down before it starts to recharge
+  pub recharge_delay: f32,
+  active: bool,
+  held: bool,
+  since_active: f32,
+}
+
+impl Default for Shield {
+  fn default() -> Self {
+    ShieldDef::default().into()
+  }
+}
+
+impl From<ShieldDef> for Shield {
+  fn from(def: ShieldDef) -> Self {
+    Self {
+      energy: def.max_energy,
+      max_energy: def.max_energy,
+      drain: def.drain,
+      recharge_rate: def.recharge_rate,
+      recharge_delay: def.recharge_delay,
+      active: false,
+      held: false,
+      since_active: 0.0,
+    }
+  }
+}
+
+impl Shield {
+  pub fn active(&self) -> bool {
+    self.active
+  }
+
+  /// Takes as much of the damage as the remaining energy allows, returns what gets through
+  pub fn absorb(&mut self, damage: f32) -> f32 {
+    if !self.active {
+      return damage;
+    }
+    let absorbed = damage.min(self.energy);
+    self.energy -= absorbed;
+    damage - absorbed
+  }
+}
+
+/// Keeps the shield of a ship up for the current frame
+#[derive(Debug)]
+pub struct ShieldCommand(pub Entity);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShieldEvent {
+  Raised(Entity),
+  Lowered(Entity),
+  /// the shield ran out of energy while up
+  Depleted(Entity),
+  /// sent by whoever applied the damage through [`Shield::absorb`]
+  Absorbed { ship: Entity, damage: f32 },
+  Recharged(Entity),
+}
+
+fn handle_cmd(mut events: EventReader<ShieldCommand>, mut qry: Query<&mut Shield>) {
+  for evt in events.iter() {
+    if let Ok(mut shield) = qry.get_mut(evt.0) {
+      shield.held = true;
+    }
+  }
+}
+
+fn update_shields(
+  mut qry: Query<(Entity, &mut Shield)>,
+  mut events: EventWriter<ShieldEvent>,
+  config: Res<RapierConfiguration>,
+  time: Res<Time>,
+) {
+  let dt = physics_dt(&config, &time);
+  for (ship, mut shield) in qry.iter_mut() {
+    let held = std::mem::take(&mut shield.held);
+
+    if held && shield.energy > 0.0 {
+      if !shield.active {
+        shield.active = true;
+        events.send(ShieldEvent::Raised(ship));
+      }
+      shield.since_active = 0.0;
+      shield.energy = (shield.energy - shield.drain * dt).max(0.0);
+      if shield.energy == 0.0 {
+        shield.active = false;
+        events.send(ShieldEvent::Depleted(ship));
+      }
+      continue;
+    }
+
+    if shield.active {
+      shield.active = false;
+      events.send(ShieldEvent::Lowered(ship));
+    }
+
+    // holding the shield while empty keeps delaying the recharge
+    shield.since_active = if held { 0.0 } else { shield.since_active + dt };
+    if shield.since_active >= shield.recharge_delay && shield.energy < shield.max_energy {
+      shield.energy = (shield.energy + shield.recharge_rate * dt).min(shield.max_energy);
+      if shield.energy == shield.max_energy {
+        events.send(ShieldEvent::Recharged(ship));
+      }
+    }
+  }
+}
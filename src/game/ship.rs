@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use utils::{
+  pickup::Stat,
+  ship::{ShipState, Spaceship},
+};
+
+use super::modifiers::Modifiers;
+
+pub struct ShipPlugin;
+impl Plugin for ShipPlugin {
+  fn build(&self, app: &mut App) {
+    app.add_system(fly_ships);
+  }
+}
+
+/// Seconds the physics advance this frame, impulses and gameplay timers have to use this instead
+/// of the frame time so replays play out the same at any frame rate
+pub fn physics_dt(config: &RapierConfiguration, time: &Time) -> f32 {
+  match config.timestep_mode {
+    TimestepMode::Fixed { dt, .. } | TimestepMode::Interpolated { dt, .. } => dt,
+    TimestepMode::Variable {
+      max_dt, time_scale, ..
+    } => (time.delta_seconds() * time_scale).min(max_dt),
+  }
+}
+
+/// Direction a ship should fly in this frame, reset after it has been applied
+#[derive(Component, Default)]
+pub struct ShipInput(pub Vec3);
+
+pub fn fly_ships(
+  mut qry: Query<(
+    &Spaceship,
+    &mut ShipInput,
+    &Transform,
+    &Velocity,
+    &mut ExternalImpulse,
+    Option<&Modifiers>,
+  )>,
+  config: Res<RapierConfiguration>,
+  time: Res<Time>,
+) {
+  let dt = physics_dt(&config, &time);
+  for (ship, mut input, transform, velocity, mut impulse, modifiers) in qry.iter_mut() {
+    let state = ShipState {
+      heading: transform.rotation * Vec3::Z,
+      velocity: velocity.linvel,
+      angular_velocity: velocity.angvel,
+    };
+    let forces = ship.forces(&state, std::mem::take(&mut input.0));
+    let speed = modifiers.map_or(1.0, |m| m.factor(Stat::Speed));
+    impulse.impulse = forces.force * speed * dt;
+    impulse.torque_impulse = forces.torque * dt;
+  }
+}
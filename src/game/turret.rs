@@ -0,0 +1,116 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use utils::loadout::TurretDef;
+
+use super::ship::physics_dt;
+
+pub struct TurretPlugin;
+impl Plugin for TurretPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .add_event::<TurretCommand>()
+      .add_system(handle_cmd)
+      .add_system(traverse_turrets.after(handle_cmd));
+  }
+}
+
+/// Child of a ship that rotates around the ship's Y axis independently of the hull
+#[derive(Component, Clone)]
+pub struct Turret {
+  /// radians per second
+  pub traverse_speed: f32,
+  /// how far the turret can turn away from the hull heading to either side, in radians, PI or
+  /// more turns all the way around
+  pub arc: f32,
+  pub target: Option<Vec3>,
+  yaw: f32,
+}
+
+impl Default for Turret {
+  fn default() -> Self {
+    Self {
+      traverse_speed: PI,
+      arc: PI,
+      target: None,
+      yaw: 0.0,
+    }
+  }
+}
+
+impl From<TurretDef> for Turret {
+  fn from(def: TurretDef) -> Self {
+    Self {
+      traverse_speed: def.traverse_speed,
+      arc: def.arc,
+      ..default()
+    }
+  }
+}
+
+#[derive(Debug)]
+pub enum TurretCommand {
+  /// Points every turret of the ship at a world position
+  Aim(Entity, Vec3),
+}
+
+fn handle_cmd(
+  mut events: EventReader<TurretCommand>,
+  qry_ship: Query<&Children>,
+  mut qry_turret: Query<&mut Turret>,
+) {
+  for evt in events.iter() {
+    match evt {
+      TurretCommand::Aim(ship, target) => {
+        let Ok(children) = qry_ship.get(*ship) else {
+          continue;
+        };
+        for child in children {
+          if let Ok(mut turret) = qry_turret.get_mut(*child) {
+            turret.target = Some(*target);
+          }
+        }
+      }
+    }
+  }
+}
+
+fn traverse_turrets(
+  mut qry_turret: Query<(&mut Turret, &mut Transform, &Parent)>,
+  qry_ship: Query<&GlobalTransform>,
+  config: Res<RapierConfiguration>,
+  time: Res<Time>,
+) {
+  let dt = physics_dt(&config, &time);
+  for (mut turret, mut transform, parent) in qry_turret.iter_mut() {
+    let (Some(target), Ok(ship)) = (turret.target, qry_ship.get(parent.get())) else {
+      continue;
+    };
+    let (_, ship_rotation, ship_translation) = ship.to_scale_rotation_translation();
+
+    let dir = target - ship_translation;
+    if dir.x.abs() < f32::EPSILON && dir.z.abs() < f32::EPSILON {
+      continue;
+    }
+    let hull_yaw = ship_rotation.to_euler(EulerRot::YXZ).0;
+    let desired = wrap_angle(dir.x.atan2(dir.z) - hull_yaw);
+    let delta = if turret.arc >= PI {
+      // free turrets take the short way round
+      wrap_angle(desired - turret.yaw)
+    } else {
+      // limited turrets can't turn through the blocked arc behind them
+      desired.clamp(-turret.arc, turret.arc) - turret.yaw
+    };
+
+    let max_step = turret.traverse_speed * dt;
+    let step = delta.clamp(-max_step, max_step);
+    turret.yaw = wrap_angle(turret.yaw + step);
+    transform.rotation = Quat::from_rotation_y(turret.yaw);
+  }
+}
+
+/// wraps to -PI..PI
+fn wrap_angle(a: f32) -> f32 {
+  (a + PI).rem_euclid(TAU) - PI
+}
@@ -0,0 +1,287 @@
+use bevy::{prelude::*, reflect::TypeUuid, utils::HashMap};
+use bevy_rapier3d::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::Deserialize;
+
+use utils::{
+  pickup::Stat,
+  ron_asset::{RonAsset, RonAssetExtensions},
+};
+
+use super::{level::OnLevel, modifiers::Modifiers, ship::physics_dt, turret::Turret};
+
+const LIBRARY_PATH: &str = "weapons/library.weapons.ron";
+
+pub struct WeaponsPlugin;
+impl Plugin for WeaponsPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .add_ron_asset::<WeaponLibrary>()
+      .add_event::<WeaponCommand>()
+      .init_resource::<WeaponLibraryHandle>()
+      .init_resource::<ProjectileAssets>()
+      .insert_resource(WeaponRng(ChaCha8Rng::seed_from_u64(0)))
+      .add_system(tick_cooldowns)
+      .add_system(handle_cmd.after(tick_cooldowns))
+      .add_system(expire_projectiles);
+  }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WeaponDef {
+  /// shots per second
+  pub fire_rate: f32,
+  pub projectile_speed: f32,
+  /// cone angle in radians that projectiles are randomly spread over
+  #[serde(default)]
+  pub spread: f32,
+  pub damage: f32,
+  /// seconds before a projectile that hit nothing is removed
+  pub lifetime: f32,
+  #[serde(default = "default_projectiles")]
+  pub projectiles: u32,
+  #[serde(default = "default_projectile_radius")]
+  pub projectile_radius: f32,
+  /// shots until the weapon is empty, unlimited if not set
+  #[serde(default)]
+  pub ammo: Option<u32>,
+}
+
+fn default_projectiles() -> u32 {
+  1
+}
+
+fn default_projectile_radius() -> f32 {
+  0.5
+}
+
+/// All weapons by name, loaded from `assets/weapons/library.weapons.ron`
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "0b2ad1f3-5e0f-4b7c-8b43-5a1a3f7e92c4"]
+pub struct WeaponLibrary {
+  pub weapons: HashMap<String, WeaponDef>,
+}
+
+impl RonAsset for WeaponLibrary {
+  const EXTENSIONS: &'static [&'static str] = &["weapons.ron"];
+}
+
+#[derive(Resource)]
+struct WeaponLibraryHandle(Handle<WeaponLibrary>);
+
+impl FromWorld for WeaponLibraryHandle {
+  fn from_world(world: &mut World) -> Self {
+    Self(world.resource::<AssetServer>().load(LIBRARY_PATH))
+  }
+}
+
+/// Shared by all projectiles, scaled to the projectile radius when spawned
+#[derive(Resource)]
+struct ProjectileAssets {
+  mesh: Handle<Mesh>,
+  material: Handle<StandardMaterial>,
+}
+
+impl FromWorld for ProjectileAssets {
+  fn from_world(world: &mut World) -> Self {
+    let mesh = world.resource_mut::<Assets<Mesh>>().add(
+      shape::UVSphere {
+        radius: 1.0,
+        ..default()
+      }
+      .into(),
+    );
+    let material = world
+      .resource_mut::<Assets<StandardMaterial>>()
+      .add(StandardMaterial {
+        emissive: Color::rgb(8.0, 4.0, 0.5),
+        ..default()
+      });
+    Self { mesh, material }
+  }
+}
+
+/// Seeded so that replaying the same inputs produces the same shots
+#[derive(Resource)]
+pub struct WeaponRng(ChaCha8Rng);
+
+impl WeaponRng {
+  pub fn reseed(&mut self, seed: u64) {
+    self.0 = ChaCha8Rng::seed_from_u64(seed);
+  }
+}
+
+/// Weapon slots of a ship, each slot refers to a weapon in the library by name
+#[derive(Component, Default)]
+pub struct Weapons {
+  pub slots: Vec<String>,
+  pub current: usize,
+  cooldown: f32,
+  /// shots fired per weapon, only counted for weapons with limited ammo
+  spent: HashMap<String, u32>,
+}
+
+impl Weapons {
+  pub fn new(slots: Vec<String>) -> Self {
+    Self {
+      slots,
+      ..default()
+    }
+  }
+
+  pub fn current(&self) -> Option<&str> {
+    self.slots.get(self.current).map(String::as_str)
+  }
+
+  /// Gives back shots to every weapon with limited ammo
+  pub fn refill(&mut self, shots: u32) {
+    for spent in self.spent.values_mut() {
+      *spent = spent.saturating_sub(shots);
+    }
+  }
+}
+
+#[derive(Component)]
+pub struct Projectile {
+  pub damage: f32,
+  /// the ship that fired the projectile
+  pub owner: Entity,
+  lifetime: f32,
+}
+
+#[derive(Debug)]
+pub enum WeaponCommand {
+  Fire(Entity),
+  Cycle(Entity),
+}
+
+fn tick_cooldowns(
+  mut qry: Query<&mut Weapons>,
+  config: Res<RapierConfiguration>,
+  time: Res<Time>,
+) {
+  let dt = physics_dt(&config, &time);
+  for mut weapons in qry.iter_mut() {
+    if weapons.cooldown > 0.0 {
+      weapons.cooldown -= dt;
+    }
+  }
+}
+
+fn handle_cmd(
+  mut cmd: Commands,
+  mut events: EventReader<WeaponCommand>,
+  mut qry: Query<(
+    &mut Weapons,
+    &Transform,
+    Option<&Velocity>,
+    Option<&Children>,
+    Option<&Modifiers>,
+  )>,
+  qry_turret: Query<&GlobalTransform, With<Turret>>,
+  mut rng: ResMut<WeaponRng>,
+  assets: Res<ProjectileAssets>,
+  library_handle: Res<WeaponLibraryHandle>,
+  libraries: Res<Assets<WeaponLibrary>>,
+) {
+  let Some(library) = libraries.get(&library_handle.0) else {
+    events.clear();
+    return;
+  };
+
+  for evt in events.iter() {
+    match evt {
+      WeaponCommand::Fire(ship) => {
+        let Ok((mut weapons, transform, velocity, children, modifiers)) = qry.get_mut(*ship)
+        else {
+          continue;
+        };
+        if weapons.cooldown > 0.0 {
+          continue;
+        }
+        let Some((name, weapon)) = weapons
+          .current()
+          .and_then(|name| library.weapons.get_key_value(name))
+        else {
+          warn!("unknown weapon {:?}", weapons.current());
+          continue;
+        };
+        if let Some(ammo) = weapon.ammo {
+          let spent = weapons.spent.entry(name.clone()).or_default();
+          if *spent >= ammo {
+            continue;
+          }
+          *spent += 1;
+        }
+        let fire_rate = weapon.fire_rate * modifiers.map_or(1.0, |m| m.factor(Stat::FireRate));
+        weapons.cooldown = 1.0 / fire_rate;
+
+        // fire from the first turret if the ship has one, along the hull otherwise
+        let turret = children
+          .and_then(|c| c.iter().find_map(|child| qry_turret.get(*child).ok()))
+          .map(|t| t.compute_transform());
+        let origin = turret.unwrap_or(*transform);
+        let forward = (origin.rotation * Vec3::Z).normalize();
+        let inherited = velocity.map_or(Vec3::ZERO, |v| v.linvel);
+
+        for _ in 0..weapon.projectiles {
+          let angle = if weapon.spread > 0.0 {
+            rng.0.gen_range(-weapon.spread / 2.0..=weapon.spread / 2.0)
+          } else {
+            0.0
+          };
+          let direction = Quat::from_rotation_y(angle) * forward;
+
+          cmd.spawn((
+            PbrBundle {
+              mesh: assets.mesh.clone(),
+              material: assets.material.clone(),
+              // spawn outside of the ship collider
+              transform: Transform::from_translation(origin.translation + direction * 6.0)
+                .with_scale(Vec3::splat(weapon.projectile_radius)),
+              ..default()
+            },
+            Projectile {
+              damage: weapon.damage,
+              owner: *ship,
+              lifetime: weapon.lifetime,
+            },
+            RigidBody::KinematicVelocityBased,
+            Velocity::linear(direction * weapon.projectile_speed + inherited),
+            Collider::ball(weapon.projectile_radius),
+            // only the mesh is scaled to the radius
+            ColliderScale::Absolute(Vec3::ONE),
+            Sensor,
+            ActiveEvents::COLLISION_EVENTS,
+            ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_STATIC,
+            OnLevel,
+          ));
+        }
+      }
+      WeaponCommand::Cycle(ship) => {
+        if let Ok((mut weapons, _, _, _, _)) = qry.get_mut(*ship) {
+          if !weapons.slots.is_empty() {
+            weapons.current = (weapons.current + 1) % weapons.slots.len();
+            info!("switched to {:?}", weapons.current());
+          }
+        }
+      }
+    }
+  }
+}
+
+fn expire_projectiles(
+  mut cmd: Commands,
+  mut qry: Query<(Entity, &mut Projectile)>,
+  config: Res<RapierConfiguration>,
+  time: Res<Time>,
+) {
+  let dt = physics_dt(&config, &time);
+  for (entity, mut projectile) in qry.iter_mut() {
+    projectile.lifetime -= dt;
+    if projectile.lifetime <= 0.0 {
+      cmd.entity(entity).despawn_recursive();
+    }
+  }
+}
@@ -3,13 +3,22 @@ use bevy::{
   core_pipeline::prepass::{DepthPrepass, NormalPrepass},
   prelude::*,
 };
-use utils::{despawn_screen, vfx::*};
+use utils::{
+  despawn_screen,
+  level::GameMode,
+  loadout::{SelectedLoadout, ShipLibrary, SHIP_LIBRARY_PATH},
+  ron_asset::RonAssetExtensions,
+  vfx::*,
+};
 
 const TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+/// shown until a ship is picked in the hangar
+const DEFAULT_PREVIEW_MODEL: &str = "ship.gltf#Scene0";
 
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
 enum MenuState {
   Main,
+  Hangar,
   #[default]
   Disabled,
 }
@@ -17,6 +26,15 @@ enum MenuState {
 #[derive(Resource)]
 struct MenuNextState<T>(T);
 
+#[derive(Resource)]
+struct ShipLibraryHandle(Handle<ShipLibrary>);
+
+impl FromWorld for ShipLibraryHandle {
+  fn from_world(world: &mut World) -> Self {
+    Self(world.resource::<AssetServer>().load(SHIP_LIBRARY_PATH))
+  }
+}
+
 pub trait MenuExtensions {
   fn add_main_menu<T: States>(&mut self, show_on_state: T, next_state: T) -> &mut Self;
 }
@@ -26,13 +44,24 @@ impl MenuExtensions for App {
     self
       .add_state::<MenuState>()
       .insert_resource(MenuNextState(next_state))
-      .add_system(menu_setup.in_schedule(OnEnter(show_on_state.clone())))
+      .add_ron_asset::<ShipLibrary>()
+      .init_resource::<ShipLibraryHandle>()
+      .init_resource::<SelectedLoadout>()
+      .init_resource::<GameMode>()
+      .add_systems((menu_setup, backdrop_setup).in_schedule(OnEnter(show_on_state.clone())))
+      .add_system(despawn_screen::<OnMenuBackdrop>.in_schedule(OnExit(show_on_state.clone())))
       .add_systems((
         main_menu_setup.in_schedule(OnEnter(MenuState::Main)),
         despawn_screen::<OnMainMenuScreen>.in_schedule(OnExit(MenuState::Main)),
+        hangar_setup.in_schedule(OnEnter(MenuState::Hangar)),
+        despawn_screen::<OnHangarScreen>.in_schedule(OnExit(MenuState::Hangar)),
         rotate_cam.in_set(OnUpdate(show_on_state.clone())),
+        update_preview.in_set(OnUpdate(show_on_state.clone())),
       ))
-      .add_systems((menu_action::<T>, button_system).in_set(OnUpdate(show_on_state.clone())))
+      .add_systems(
+        (menu_action::<T>, select_ship, button_system.after(select_ship))
+          .in_set(OnUpdate(show_on_state.clone())),
+      )
   }
 }
 
@@ -41,16 +70,21 @@ fn menu_action<T: States>(
   mut app_exit_events: EventWriter<AppExit>,
   mut menu_state: ResMut<NextState<MenuState>>,
   mut game_state: ResMut<NextState<T>>,
+  mut game_mode: ResMut<GameMode>,
   next_state: Res<MenuNextState<T>>,
 ) {
   for (interaction, menu_button_action) in &interaction_query {
     if *interaction == Interaction::Clicked {
       match menu_button_action {
         MenuButtonAction::Quit => app_exit_events.send(AppExit),
-        MenuButtonAction::Play => {
+        MenuButtonAction::Play(mode) => {
+          *game_mode = *mode;
           game_state.set(next_state.0.clone());
           menu_state.set(MenuState::Disabled);
         }
+        MenuButtonAction::Hangar => menu_state.set(MenuState::Hangar),
+        MenuButtonAction::BackToMain => menu_state.set(MenuState::Main),
+        MenuButtonAction::SelectShip(_) => {}
       }
     }
   }
@@ -59,6 +93,16 @@ fn menu_action<T: States>(
 #[derive(Component)]
 struct OnMainMenuScreen;
 
+#[derive(Component)]
+struct OnHangarScreen;
+
+/// camera, ship preview and skybox, kept while moving between menu screens
+#[derive(Component)]
+struct OnMenuBackdrop;
+
+#[derive(Component)]
+struct ShipPreview;
+
 const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
 const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
 const HOVERED_PRESSED_BUTTON: Color = Color::rgb(0.25, 0.65, 0.25);
@@ -69,7 +113,11 @@ struct SelectedOption;
 
 #[derive(Component)]
 enum MenuButtonAction {
-  Play,
+  Play(GameMode),
+  Hangar,
+  BackToMain,
+  /// index into the ship library
+  SelectShip(usize),
   Quit,
 }
 
@@ -93,8 +141,54 @@ fn menu_setup(mut menu_state: ResMut<NextState<MenuState>>) {
   menu_state.set(MenuState::Main);
 }
 
-fn main_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-  let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+/// Picks the loadout and moves the highlight to the clicked ship
+fn select_ship(
+  interaction_query: Query<
+    (Entity, &Interaction, &MenuButtonAction),
+    (Changed<Interaction>, With<Button>),
+  >,
+  mut selected_query: Query<(Entity, &mut BackgroundColor), With<SelectedOption>>,
+  mut commands: Commands,
+  mut selected: ResMut<SelectedLoadout>,
+  library_handle: Res<ShipLibraryHandle>,
+  libraries: Res<Assets<ShipLibrary>>,
+) {
+  for (entity, interaction, action) in &interaction_query {
+    let (Interaction::Clicked, MenuButtonAction::SelectShip(index)) = (interaction, action) else {
+      continue;
+    };
+    let Some(ship) = libraries.get(&library_handle.0).and_then(|l| l.ships.get(*index)) else {
+      continue;
+    };
+    info!("selected {}", ship.name);
+    selected.0 = Some(ship.clone());
+    for (previous, mut color) in &mut selected_query {
+      *color = NORMAL_BUTTON.into();
+      commands.entity(previous).remove::<SelectedOption>();
+    }
+    commands.entity(entity).insert(SelectedOption);
+  }
+}
+
+fn update_preview(
+  selected: Res<SelectedLoadout>,
+  asset_server: Res<AssetServer>,
+  mut query: Query<&mut Handle<Scene>, With<ShipPreview>>,
+) {
+  if !selected.is_changed() {
+    return;
+  }
+  let model = selected.0.as_ref().map_or(DEFAULT_PREVIEW_MODEL, |s| s.model.as_str());
+  for mut scene in &mut query {
+    *scene = asset_server.load(model);
+  }
+}
+
+fn backdrop_setup(
+  mut commands: Commands,
+  asset_server: Res<AssetServer>,
+  selected: Res<SelectedLoadout>,
+) {
   commands.spawn((
     Camera3dBundle {
       transform: Transform::from_xyz(-2.0, 4.0, 8.0).looking_at(Vec3::ZERO, Vec3::Y),
@@ -105,26 +199,32 @@ fn main_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
       },
       ..default()
     },
-    OnMainMenuScreen,
+    OnMenuBackdrop,
     DepthPrepass,
     NormalPrepass,
     PostProcessSettings::default(),
   ));
 
+  let model = selected.0.as_ref().map_or(DEFAULT_PREVIEW_MODEL, |s| s.model.as_str());
   commands.spawn((
     SceneBundle {
-      scene: asset_server.load("ship.gltf#Scene0"),
+      scene: asset_server.load(model),
       ..default()
     },
-    OnMainMenuScreen,
+    ShipPreview,
+    OnMenuBackdrop,
   ));
 
   commands.spawn((
     Cubemap {
       image: asset_server.load("skybox/cubemap.png"),
     },
-    OnMainMenuScreen,
+    OnMenuBackdrop,
   ));
+}
+
+fn main_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+  let font = asset_server.load("fonts/FiraSans-Bold.ttf");
 
   // Common style for all buttons on the screen
   let button_style = Style {
@@ -211,7 +311,7 @@ fn main_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                 background_color: NORMAL_BUTTON.into(),
                 ..default()
               },
-              MenuButtonAction::Play,
+              MenuButtonAction::Play(GameMode::Campaign),
             ))
             .with_children(|parent| {
               let icon = asset_server.load("ui/right.png");
@@ -223,6 +323,32 @@ fn main_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
               parent.spawn(TextBundle::from_section("Play", button_text_style.clone()));
             });
 
+          parent
+            .spawn((
+              ButtonBundle {
+                style: button_style.clone(),
+                background_color: NORMAL_BUTTON.into(),
+                ..default()
+              },
+              MenuButtonAction::Play(GameMode::Sector),
+            ))
+            .with_children(|parent| {
+              parent.spawn(TextBundle::from_section("Sector", button_text_style.clone()));
+            });
+
+          parent
+            .spawn((
+              ButtonBundle {
+                style: button_style.clone(),
+                background_color: NORMAL_BUTTON.into(),
+                ..default()
+              },
+              MenuButtonAction::Hangar,
+            ))
+            .with_children(|parent| {
+              parent.spawn(TextBundle::from_section("Hangar", button_text_style.clone()));
+            });
+
           parent
             .spawn((
               ButtonBundle {
@@ -245,6 +371,84 @@ fn main_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     });
 }
 
+fn hangar_setup(
+  mut commands: Commands,
+  asset_server: Res<AssetServer>,
+  library_handle: Res<ShipLibraryHandle>,
+  libraries: Res<Assets<ShipLibrary>>,
+  selected: Res<SelectedLoadout>,
+) {
+  let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+  let button_style = Style {
+    size: Size::new(Val::Px(250.0), Val::Px(65.0)),
+    margin: UiRect::all(Val::Px(20.0)),
+    justify_content: JustifyContent::Center,
+    align_items: AlignItems::Center,
+    ..default()
+  };
+  let button_text_style = TextStyle {
+    font,
+    font_size: 40.0,
+    color: TEXT_COLOR,
+  };
+
+  let ships: &[_] = match libraries.get(&library_handle.0) {
+    Some(library) => library.ships.as_slice(),
+    None => {
+      warn!("ship library {} is not loaded", SHIP_LIBRARY_PATH);
+      &[]
+    }
+  };
+  let selected_name = selected.0.as_ref().map(|s| s.name.as_str());
+
+  commands
+    .spawn((
+      NodeBundle {
+        style: Style {
+          size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+          flex_direction: FlexDirection::Column,
+          align_items: AlignItems::End,
+          justify_content: JustifyContent::Center,
+          ..default()
+        },
+        ..default()
+      },
+      OnHangarScreen,
+    ))
+    .with_children(|parent| {
+      for (index, ship) in ships.iter().enumerate() {
+        let is_selected = selected_name == Some(ship.name.as_str());
+        let mut button = parent.spawn((
+          ButtonBundle {
+            style: button_style.clone(),
+            background_color: if is_selected { PRESSED_BUTTON } else { NORMAL_BUTTON }.into(),
+            ..default()
+          },
+          MenuButtonAction::SelectShip(index),
+        ));
+        if is_selected {
+          button.insert(SelectedOption);
+        }
+        button.with_children(|parent| {
+          parent.spawn(TextBundle::from_section(&ship.name, button_text_style.clone()));
+        });
+      }
+
+      parent
+        .spawn((
+          ButtonBundle {
+            style: button_style,
+            background_color: NORMAL_BUTTON.into(),
+            ..default()
+          },
+          MenuButtonAction::BackToMain,
+        ))
+        .with_children(|parent| {
+          parent.spawn(TextBundle::from_section("Back", button_text_style));
+        });
+    });
+}
+
 fn rotate_cam(time: Res<Time>, mut query: Query<&mut Transform, With<Camera>>) {
   for mut transform in &mut query {
     transform.rotate_around(